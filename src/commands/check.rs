@@ -1,23 +1,253 @@
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Parser;
-use log::info;
+use log::{error, info, warn};
+use typst_syntax::is_ident;
+use typst_syntax::package::PackageManifest;
 
 use crate::utils::read_manifest;
+use crate::utils::walkers::walker_install;
 
-const LONG_ABOUT: &str = "Check if the package is valid (WIP). Must be in the package directory.";
+const LONG_ABOUT: &str = "Check if the package is valid and follows the submission guidelines of Typst Universe. Errors make the command fail; warnings point out likely mistakes. `publish` runs the same checks. Must be in the package directory.";
+
+/// Files of at least this size are reported
+const LARGE_FILE_SIZE: u64 = 1024 * 1024;
+/// Universe allows at most this many categories
+const MAX_CATEGORIES: usize = 3;
 
 #[derive(Parser)]
 #[command(long_about = LONG_ABOUT)]
-/// Check if the package is valid (WIP).
+/// Check if the package is valid
 pub struct CheckArgs {}
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found by [`diagnose`].
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
 pub fn check(package_dir: &Path) -> Result<()> {
-    let _current = read_manifest(package_dir)?;
-    // TODO: warn empty fields? check glob?
-    // TODO: move init validations here (or use a common function)
-    // TODO: reference: Typst universe CI checks
-    info!("No issues found");
+    let errors = run_checks(package_dir)?;
+    if errors > 0 {
+        bail!("Found {} error(s)", errors);
+    }
     Ok(())
 }
+
+/// Log the problems of the package in `package_dir`, returning the number of
+/// errors.
+pub fn run_checks(package_dir: &Path) -> Result<usize> {
+    let diagnostics = diagnose(package_dir)?;
+    for diagnostic in &diagnostics {
+        match diagnostic.severity {
+            Severity::Error => error!("{}", diagnostic),
+            Severity::Warning => warn!("{}", diagnostic),
+        }
+    }
+    if diagnostics.is_empty() {
+        info!("No issues found");
+    }
+    Ok(diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count())
+}
+
+/// Run every check on the package in `package_dir`. Fails only if the
+/// manifest cannot be read.
+pub fn diagnose(package_dir: &Path) -> Result<Vec<Diagnostic>> {
+    let manifest = read_manifest(package_dir)?;
+    let mut diagnostics = check_manifest(&manifest);
+    diagnostics.extend(check_files(package_dir, &manifest));
+    Ok(diagnostics)
+}
+
+fn check_manifest(manifest: &PackageManifest) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let package = &manifest.package;
+    if !is_ident(&package.name) {
+        diagnostics.push(Diagnostic::error(format!(
+            "`{}` is not a valid package name",
+            package.name
+        )));
+    } else if package.name.chars().any(|c| c.is_uppercase() || c == '_') {
+        diagnostics.push(Diagnostic::warning(format!(
+            "Package names should be in kebab-case, unlike `{}`",
+            package.name
+        )));
+    }
+    if package.authors.is_empty() {
+        diagnostics.push(Diagnostic::error("No `authors` in `typst.toml`"));
+    }
+    if package.license.is_none() {
+        diagnostics.push(Diagnostic::error("No `license` in `typst.toml`"));
+    }
+    match &package.description {
+        None => diagnostics.push(Diagnostic::error("No `description` in `typst.toml`")),
+        Some(desc) if !desc.trim_end().ends_with(['.', '!', '?']) => diagnostics.push(
+            Diagnostic::warning("The `description` should be a sentence ending with a full stop"),
+        ),
+        Some(_) => {}
+    }
+    if package.categories.len() > MAX_CATEGORIES {
+        diagnostics.push(Diagnostic::error(format!(
+            "At most {} `categories` are allowed",
+            MAX_CATEGORIES
+        )));
+    }
+    if package.repository.is_none() && package.homepage.is_none() {
+        diagnostics.push(Diagnostic::warning(
+            "Neither `repository` nor `homepage` is set in `typst.toml`",
+        ));
+    }
+    if package.compiler.is_none() {
+        diagnostics.push(Diagnostic::warning(
+            "No minimal `compiler` version in `typst.toml`",
+        ));
+    }
+    diagnostics
+}
+
+fn check_files(package_dir: &Path, manifest: &PackageManifest) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let walker = match walker_install(package_dir) {
+        Ok(walker) => walker,
+        Err(e) => return vec![Diagnostic::error(format!("Invalid `exclude`: {}", e))],
+    };
+    // The files that end up in the published package
+    let files = walker
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| {
+            entry
+                .path()
+                .strip_prefix(package_dir)
+                .unwrap()
+                .to_path_buf()
+        })
+        .collect::<Vec<_>>();
+    let published = |path: &Path| files.iter().any(|file| file == path);
+
+    let entrypoint = Path::new(manifest.package.entrypoint.as_str());
+    if !published(entrypoint) {
+        diagnostics.push(Diagnostic::error(format!(
+            "The entrypoint `{}` does not exist or is excluded",
+            entrypoint.display()
+        )));
+    }
+    if !published(Path::new("README.md")) {
+        diagnostics.push(Diagnostic::error("No `README.md` documenting the package"));
+    }
+    let license_files = license_files(package_dir);
+    if license_files.is_empty() {
+        diagnostics.push(Diagnostic::warning(
+            "No `LICENSE` file. Make sure the `README.md` links to the license",
+        ));
+    }
+    for file in license_files.iter().filter(|file| !published(file)) {
+        diagnostics.push(Diagnostic::error(format!(
+            "`{}` must not be excluded",
+            file.display()
+        )));
+    }
+
+    if let Some(template) = &manifest.template {
+        let template_dir = Path::new(template.path.as_str());
+        let entrypoint = template_dir.join(template.entrypoint.as_str());
+        if !files.iter().any(|file| file.starts_with(template_dir)) {
+            diagnostics.push(Diagnostic::error(format!(
+                "The template directory `{}` does not exist or is excluded",
+                template.path
+            )));
+        } else if !published(&entrypoint) {
+            diagnostics.push(Diagnostic::error(format!(
+                "The template entrypoint `{}` does not exist or is excluded",
+                entrypoint.display()
+            )));
+        }
+        match &template.thumbnail {
+            None => diagnostics.push(Diagnostic::error("No `thumbnail` for the template")),
+            Some(thumbnail) if !package_dir.join(thumbnail.as_str()).is_file() => {
+                diagnostics.push(Diagnostic::error(format!(
+                    "The template thumbnail `{}` does not exist",
+                    thumbnail
+                )))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for file in &files {
+        if file.extension().is_some_and(|ext| ext == "pdf") {
+            diagnostics.push(Diagnostic::warning(format!(
+                "`{}` is published. Exclude PDFs unless the package needs them",
+                file.display()
+            )));
+        }
+        let size = package_dir
+            .join(file)
+            .metadata()
+            .map(|m| m.len())
+            .unwrap_or_default();
+        if size >= LARGE_FILE_SIZE {
+            diagnostics.push(Diagnostic::warning(format!(
+                "`{}` is large ({} KiB). Exclude it if the package does not need it",
+                file.display(),
+                size / 1024
+            )));
+        }
+    }
+    diagnostics
+}
+
+/// `LICENSE`, `LICENSE-MIT`, `COPYING`, etc. at the root of the package.
+pub fn license_files(package_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = package_dir.read_dir() else {
+        return vec![];
+    };
+    let mut files = entries
+        .flatten()
+        .map(|entry| PathBuf::from(entry.file_name()))
+        .filter(|name| {
+            let name = name.to_string_lossy().to_uppercase();
+            ["LICENSE", "LICENCE", "COPYING"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        })
+        .filter(|name| package_dir.join(name).is_file())
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
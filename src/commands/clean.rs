@@ -1,8 +1,11 @@
+use std::str::FromStr;
+
 use anyhow::{bail, Result};
 use clap::Parser;
 use log::{info, warn};
+use typst_syntax::package::{PackageSpec, PackageVersion};
 
-use crate::utils::typst_local_dir;
+use crate::utils::{remove_receipt, typst_local_dir};
 
 const LONG_ABOUT: &str =
     "Clean the existing dev symlinks of all packages (or a certain package) in the data directory.";
@@ -50,6 +53,16 @@ pub fn clean_one(name: &str) -> Result<()> {
             let target = symlink.read_link()?;
             if target.is_dir() {
                 std::fs::remove_dir_all(symlink)?;
+                if let Ok(version) =
+                    PackageVersion::from_str(version.file_name().to_string_lossy().as_ref())
+                {
+                    let spec = PackageSpec {
+                        namespace: "preview".into(),
+                        name: name.into(),
+                        version,
+                    };
+                    remove_receipt(&typst_local_dir(), &spec)?;
+                }
                 info!(
                     "Removed symlink of version `{}`",
                     version.file_name().to_string_lossy()
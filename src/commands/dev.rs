@@ -3,10 +3,14 @@ use std::path::Path;
 use anyhow::{bail, Result};
 use clap::Parser;
 use log::{debug, info, warn};
+use typst_syntax::package::PackageSpec;
 
 use crate::commands::clean::CleanArgs;
-use crate::regs::universe::{package_versions, packages};
-use crate::utils::{read_manifest, typst_local_dir};
+use crate::config::CONFIG;
+use crate::model::receipt::{InstallKind, Receipt, Source};
+use crate::regs::credentials::universe_token;
+use crate::regs::universe::{package_versions, packages, Upstream};
+use crate::utils::{read_manifest, symlink_dir, typst_local_dir, write_receipt};
 
 use super::clean::clean;
 
@@ -35,18 +39,17 @@ pub async fn dev(package_dir: &Path) -> Result<()> {
         );
     }
 
-    if packages()
+    let upstream = Upstream::from_config(&CONFIG.try_lock()?.universe);
+    // Any token raises the rate limit, but none is needed
+    let token = universe_token(None, &upstream.host())?.map(|(token, _)| token);
+    if packages(&upstream, token.as_ref())
         .await?
-        .items
-        .into_iter()
-        .any(|p| p.name == current.package.name)
+        .iter()
+        .any(|p| *p == current.package.name)
     {
-        if package_versions(&current.package.name)
+        if package_versions(&upstream, token.as_ref(), &current.package.name)
             .await?
-            .items
-            .into_iter()
-            .map(|v| v.name)
-            .any(|v| v == version.to_string())
+            .contains(&version.to_string())
         {
             warn!("Version `{}` is already available in the Universe", version);
         }
@@ -80,14 +83,7 @@ pub async fn dev(package_dir: &Path) -> Result<()> {
         package_dir.display(),
         version_dir.display()
     );
-    #[cfg(unix)]
-    {
-        std::os::unix::fs::symlink(package_dir, &version_dir)?;
-    }
-    #[cfg(windows)]
-    {
-        std::os::windows::fs::symlink_dir(package_dir, &version_dir)?;
-    }
+    symlink_dir(package_dir, &version_dir)?;
 
     if version_dir.is_symlink() {
         info!("Symlink created successfully");
@@ -95,5 +91,18 @@ pub async fn dev(package_dir: &Path) -> Result<()> {
         bail!("Failed to create symlink");
     }
 
+    let spec = PackageSpec {
+        namespace: "preview".into(),
+        name: current.package.name.clone(),
+        version,
+    };
+    let source = Source::Path {
+        path: package_dir.to_path_buf(),
+    };
+    write_receipt(
+        &typst_local_dir(),
+        &Receipt::new(&spec, InstallKind::Dev, source),
+    )?;
+
     Ok(())
 }
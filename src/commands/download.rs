@@ -1,84 +1,498 @@
-use std::fs;
-use std::path::PathBuf;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
-use log::info;
+use log::{error, info};
+use typst_syntax::package::{PackageSpec, PackageVersion};
 
-use crate::commands::install::{install, InstallArgs};
-use crate::utils::temp_subdir;
+use crate::commands::install::{install_archive, install_from, validate_package, InstallOptions};
+use crate::model::package_list::{PackageList, PackageListEntry};
+use crate::model::receipt::Source;
+use crate::model::version::{version_from_tag, VersionReq};
+use crate::prompt::{LockedPrompter, Prompter};
+use crate::utils::git::{self, CloneOptions};
+use crate::utils::temp::WorkDir;
+use crate::utils::walkers::find_packages;
+use crate::utils::{
+    package_file_hashes, package_root, read_manifest, tree_sha256, typst_local_dir, unpack_archive,
+};
 
-const LONG_ABOUT: &str = "Download a package from git repository to a certain (defaults to `@local`) namespace. You may specify a specific tag, commit, or branch to checkout to.";
+/// At most this many packages of a list are downloaded at once
+const PARALLEL_DOWNLOADS: usize = 4;
+
+/// Where package bundles are served from, as `<url>/<namespace>/<name>-<version>.tar.gz`
+pub const DEFAULT_REGISTRY_URL: &str = "https://packages.typst.org";
+
+const LONG_ABOUT: &str = "Download a package from git repository, or a published package from Typst Universe (e.g. `@preview/foo:0.1.0`), to a certain (defaults to `@local`) namespace. You may specify a specific tag, commit, or branch to checkout to, or a version requirement (e.g. `^0.3`) to pick the highest matching version tag. If the repository holds several packages, select one with `--path` or `--name`, all of them with `--all`, or choose interactively. Before anything is installed, the package is checked against the request: its name (`--name`), its version (for a version tag), and the SHA-256 of its files (`--sha256`). To set up many packages at once, list them in a TOML file and pass it with `--from`.";
 
 #[derive(Parser)]
 #[command(long_about = LONG_ABOUT)]
-/// Download a package from git repository to a certain (defaults to `@local`)
-/// namespace
+/// Download a package from git repository or Typst Universe to a certain
+/// (defaults to `@local`) namespace
 pub struct DownloadArgs {
-    /// Git repository URL
-    pub repository: String,
+    #[arg(required_unless_present = "from")]
+    /// Git repository URL, or a published package such as `@preview/foo:0.1.0`
+    pub repository: Option<String>,
 
     #[arg(short, long, value_name = "REF")]
     /// Checkout to a specific tag, commit, or branch
     pub checkout: Option<String>,
 
+    #[arg(short, long, value_name = "REQ", conflicts_with = "checkout")]
+    #[arg(
+        long_help = "Download the highest version matching the requirement (e.g. `^0.3`, `~0.3.1`, `>=0.2, <0.4`). Tags are read as versions, with or without a leading `v`, and the version in `typst.toml` must agree with the tag (unless several packages are installed from the repository)."
+    )]
+    /// Download the highest version tag matching a requirement
+    pub version: Option<VersionReq>,
+
     #[arg(short, long, default_value = "local")]
     /// Namespace to install the package to (without the `@` prefix)
     pub namespace: String,
+
+    #[arg(short, long, value_name = "SUBDIR", conflicts_with = "all")]
+    /// Directory of the package within the repository
+    pub path: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Install every package found in the repository
+    pub all: bool,
+
+    #[arg(long, conflicts_with = "all")]
+    #[arg(
+        long_help = "The name of the package. It selects the package if the repository holds several, and the download aborts if the package has another name."
+    )]
+    /// The expected name of the package
+    pub name: Option<String>,
+
+    #[arg(long, value_name = "HEX", conflicts_with = "all")]
+    #[arg(
+        long_help = "The expected SHA-256 of the package files, as printed after every download. The download aborts if the package differs, e.g. because a tag was moved."
+    )]
+    /// The expected SHA-256 of the package files
+    pub sha256: Option<String>,
+
+    #[arg(long, value_name = "URL", default_value = DEFAULT_REGISTRY_URL)]
+    /// Base URL to download published packages from
+    pub registry: String,
+
+    #[arg(long)]
+    /// Keep the cloned repositories in the temp directory, for debugging
+    pub keep: bool,
+
+    #[arg(long, value_name = "FILE")]
+    #[arg(conflicts_with_all = ["repository", "checkout", "version", "path", "all", "name", "sha256"])]
+    #[arg(
+        long_help = "Install every package listed in a TOML file, up to 4 at a time. Each `[[package]]` sets exactly one source: `git` (with optional `ref` or `version`, and `path` or `all`), `archive` (a `.tar.gz` bundle, relative to the file), or `universe` (e.g. `@preview/cetz:0.3.1`). `namespace` defaults to `--namespace`. `name` and `sha256` work like the options of the same name."
+    )]
+    /// Install every package listed in a TOML file
+    pub from: Option<PathBuf>,
 }
 
-pub fn download(args: &DownloadArgs) -> Result<()> {
-    let temp_dir = temp_subdir(&args.repository);
-    fs::create_dir_all(&temp_dir)?;
+/// A git repository to download packages from.
+struct GitSource {
+    url: String,
+    checkout: Option<String>,
+    version: Option<VersionReq>,
+    path: Option<PathBuf>,
+    all: bool,
+    name: Option<String>,
+    sha256: Option<String>,
+}
 
-    let res = temp_jobs(
-        temp_dir.clone(),
-        &args.repository,
-        args.checkout.as_deref(),
-        &args.namespace,
-    );
-    fs::remove_dir_all(&temp_dir)?;
-    res?;
+pub fn download(args: &DownloadArgs, prompter: &dyn Prompter) -> Result<()> {
+    if let Some(from) = &args.from {
+        return download_list(from, args, prompter);
+    }
+
+    let repository = args.repository.as_deref().unwrap_or_default();
+    if repository.starts_with('@') {
+        if args.checkout.is_some()
+            || args.version.is_some()
+            || args.path.is_some()
+            || args.all
+            || args.name.is_some()
+        {
+            bail!("`--checkout`, `--version`, `--path`, `--all` and `--name` only apply to git repositories");
+        }
+        let spec = PackageSpec::from_str(repository).map_err(|e| anyhow!(e))?;
+        download_bundle(
+            &typst_local_dir(),
+            &spec,
+            &args.registry,
+            args.sha256.as_deref(),
+            &args.namespace,
+            prompter,
+        )?;
+    } else {
+        let source = GitSource {
+            url: repository.to_string(),
+            checkout: args.checkout.clone(),
+            version: args.version.clone(),
+            path: args.path.clone(),
+            all: args.all,
+            name: args.name.clone(),
+            sha256: args.sha256.clone(),
+        };
+        let work_dir = WorkDir::new("download", args.keep)?;
+        download_git(work_dir.path(), &source, &args.namespace, prompter)?;
+    }
 
     info!("Done");
     Ok(())
 }
 
-fn temp_jobs(temp_dir: PathBuf, repo: &str, checkout: Option<&str>, namespace: &str) -> Result<()> {
-    info!("Cloning the repository...");
-    fs::remove_dir_all(&temp_dir)?;
-    fs::create_dir_all(&temp_dir)?;
-    let status = std::process::Command::new("git")
-        .arg("clone")
-        // .arg("--depth=1")
-        .arg(repo)
-        .arg(&temp_dir)
-        .current_dir(&temp_dir)
-        .status()
-        .expect("Failed to run git clone");
-    if !status.success() {
-        bail!("Failed to clone");
+/// Download every package in the list file at `path`, a few in parallel,
+/// then summarize the results.
+fn download_list(path: &Path, args: &DownloadArgs, prompter: &dyn Prompter) -> Result<()> {
+    let list = PackageList::from_file(path)?;
+    if list.package.is_empty() {
+        bail!("No packages listed in {}", path.display());
     }
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let prompter = LockedPrompter::new(prompter);
 
-    if let Some(checkout) = checkout {
-        info!("Checking out to {}...", checkout);
-        let status = std::process::Command::new("git")
-            .arg("checkout")
-            .arg(checkout)
-            .current_dir(&temp_dir)
-            .status()
-            .expect("Failed to run git checkout");
-        if !status.success() {
-            bail!("Failed to checkout");
+    // A few workers take the next entry until none are left
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|s| {
+        let workers = (0..PARALLEL_DOWNLOADS.min(list.package.len()))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(entry) = list.package.get(index) else {
+                            return results;
+                        };
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                            download_entry(entry, base_dir, args, &prompter)
+                        }))
+                        .unwrap_or_else(|_| Err(anyhow!("Panicked")));
+                        results.push((index, result));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_default())
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    let results = results
+        .into_iter()
+        .map(|(_, result)| result)
+        .collect::<Vec<_>>();
+
+    let failed = results.iter().filter(|r| r.is_err()).count();
+    info!(
+        "Summary: {} succeeded, {} failed",
+        results.len() - failed,
+        failed
+    );
+    for (entry, result) in list.package.iter().zip(&results) {
+        match result {
+            Ok(()) => info!("\tok      {}", entry),
+            Err(e) => error!("\tfailed  {}: {:#}", entry, e),
         }
     }
+    if failed > 0 {
+        bail!("{} of {} packages failed", failed, results.len());
+    }
+    Ok(())
+}
 
-    info!("Installing...");
-    install(
-        &temp_dir,
-        &InstallArgs {
-            target: namespace.to_string(),
-        },
+fn download_entry(
+    entry: &PackageListEntry,
+    base_dir: &Path,
+    args: &DownloadArgs,
+    prompter: &dyn Prompter,
+) -> Result<()> {
+    let namespace = entry.namespace.as_deref().unwrap_or(&args.namespace);
+    if let Some(url) = &entry.git {
+        let source = GitSource {
+            url: url.clone(),
+            checkout: entry.checkout.clone(),
+            version: entry.version.as_deref().map(str::parse).transpose()?,
+            path: entry.path.clone(),
+            all: entry.all,
+            name: entry.name.clone(),
+            sha256: entry.sha256.clone(),
+        };
+        let work_dir = WorkDir::new("download", args.keep)?;
+        download_git(work_dir.path(), &source, namespace, prompter)
+    } else if let Some(archive) = &entry.archive {
+        install_archive(
+            &typst_local_dir(),
+            &base_dir.join(archive),
+            &InstallOptions::new(namespace),
+            prompter,
+        )
+    } else if let Some(spec) = &entry.universe {
+        let spec = PackageSpec::from_str(spec).map_err(|e| anyhow!(e))?;
+        download_bundle(
+            &typst_local_dir(),
+            &spec,
+            &args.registry,
+            entry.sha256.as_deref(),
+            namespace,
+            prompter,
+        )
+    } else {
+        unreachable!("validated by `PackageList::from_file`")
+    }
+}
+
+/// Clone `source` into the empty `temp_dir` and install the selected packages.
+fn download_git(
+    temp_dir: &Path,
+    source: &GitSource,
+    namespace: &str,
+    prompter: &dyn Prompter,
+) -> Result<()> {
+    let repo = source.url.as_str();
+    let tag_version = if let Some(req) = &source.version {
+        let (tag, version) = resolve_tag(repo, req)?;
+        info!("Cloning tag `{}` (version {})...", tag, version);
+        let options = CloneOptions {
+            branch: Some(&tag),
+            shallow: true,
+            ..Default::default()
+        };
+        git::clone(repo, temp_dir, options)?;
+        Some(version)
+    } else {
+        info!("Cloning the repository...");
+        git::clone(repo, temp_dir, CloneOptions::default())?;
+        if let Some(checkout) = &source.checkout {
+            info!("Checking out to {}...", checkout);
+            git::checkout(temp_dir, checkout)?;
+        }
+        None
+    };
+
+    let commit = git::head_commit(temp_dir)?;
+    info!("Resolved to commit {}", commit);
+
+    let package_dirs = match (&source.path, &source.name) {
+        (Some(path), _) => {
+            let package_dir = temp_dir.join(path);
+            if !package_dir.join("typst.toml").is_file() {
+                bail!("No `typst.toml` found in `{}`", path.display());
+            }
+            vec![package_dir]
+        }
+        (None, Some(name)) => {
+            let package_dir = find_packages(temp_dir)
+                .into_iter()
+                .find(|dir| read_manifest(dir).is_ok_and(|m| m.package.name == name.as_str()))
+                .ok_or(anyhow!(
+                    "No package named `{}` found in the repository",
+                    name
+                ))?;
+            vec![package_dir]
+        }
+        (None, None) => select_packages(temp_dir, source.all, prompter)?,
+    };
+    // A version tag, whether picked by `version` or checked out by hand
+    let version = tag_version.or_else(|| source.checkout.as_deref().and_then(version_from_tag));
+    verify_packages(
+        &package_dirs,
+        source.name.as_deref(),
+        version,
+        source.sha256.as_deref(),
     )?;
+
+    for package_dir in package_dirs {
+        let subdir = package_dir
+            .strip_prefix(temp_dir)
+            .ok()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(Path::to_path_buf);
+        info!("Installing...");
+        install_from(
+            &typst_local_dir(),
+            &package_dir,
+            &InstallOptions::new(namespace),
+            Source::Git {
+                url: repo.to_string(),
+                commit: Some(commit.clone()),
+                subdir,
+            },
+            prompter,
+        )?;
+    }
+    Ok(())
+}
+
+/// Download the bundle of a published package from `registry` and install it
+/// into `packages_dir`.
+pub fn download_bundle(
+    packages_dir: &Path,
+    spec: &PackageSpec,
+    registry: &str,
+    sha256: Option<&str>,
+    namespace: &str,
+    prompter: &dyn Prompter,
+) -> Result<()> {
+    let url = format!(
+        "{}/{}/{}-{}.tar.gz",
+        registry.trim_end_matches('/'),
+        spec.namespace,
+        spec.name,
+        spec.version
+    );
+    info!("Downloading {}...", url);
+    let response = ureq::get(&url).call().map_err(|e| match e {
+        ureq::Error::Status(404, _) => anyhow!("`{}` is not published at {}", spec, registry),
+        e => anyhow!(e).context(format!("Failed to download {}", url)),
+    })?;
+
+    let temp_dir = tempfile::tempdir()?;
+    unpack_archive(response.into_reader(), temp_dir.path())
+        .with_context(|| format!("Failed to read {}", url))?;
+    let package_dir = package_root(temp_dir.path())?;
+    validate_package(&package_dir)?;
+    verify_package(&package_dir, Some(&spec.name), Some(spec.version), sha256)?;
+
+    info!("Installing...");
+    install_from(
+        packages_dir,
+        &package_dir,
+        &InstallOptions::new(namespace),
+        Source::Registry { url },
+        prompter,
+    )
+}
+
+/// Check the packages in `package_dirs`, found in the same checkout, against
+/// what was asked for. The version of a tag is only checked if there is a
+/// single package, since a tag of a repository with several packages is
+/// usually the version of just one of them.
+pub fn verify_packages(
+    package_dirs: &[PathBuf],
+    name: Option<&str>,
+    version: Option<PackageVersion>,
+    sha256: Option<&str>,
+) -> Result<()> {
+    if sha256.is_some() && package_dirs.len() > 1 {
+        bail!("`sha256` can only be checked for a single package");
+    }
+    let version = match version {
+        Some(version) if package_dirs.len() > 1 => {
+            info!(
+                "Not checking the version {} of the tag against {} packages",
+                version,
+                package_dirs.len()
+            );
+            None
+        }
+        version => version,
+    };
+    for package_dir in package_dirs {
+        verify_package(package_dir, name, version, sha256)?;
+    }
     Ok(())
 }
+
+/// Check the package in `package_dir` against what was asked for, so that a
+/// mismatch aborts before anything is installed.
+fn verify_package(
+    package_dir: &Path,
+    name: Option<&str>,
+    version: Option<PackageVersion>,
+    sha256: Option<&str>,
+) -> Result<()> {
+    let manifest = read_manifest(package_dir)?;
+    let actual = format!("{}:{}", manifest.package.name, manifest.package.version);
+    if let Some(name) = name {
+        if manifest.package.name != name {
+            bail!("Expected package `{}`, but found `{}`", name, actual);
+        }
+    }
+    if let Some(version) = version {
+        if manifest.package.version != version {
+            bail!("Expected version {}, but found `{}`", version, actual);
+        }
+    }
+    let tree_hash = tree_sha256(&package_file_hashes(package_dir)?);
+    if let Some(sha256) = sha256 {
+        if !tree_hash.eq_ignore_ascii_case(sha256.trim()) {
+            bail!(
+                "The SHA-256 of `{}` is {}, but {} was expected",
+                actual,
+                tree_hash,
+                sha256
+            );
+        }
+    }
+    info!("SHA-256 of `{}`: {}", actual, tree_hash);
+    Ok(())
+}
+
+/// Find the tag of the highest version in `repo` matching `req`.
+fn resolve_tag(repo: &str, req: &VersionReq) -> Result<(String, PackageVersion)> {
+    info!("Listing the tags...");
+    let tags = git::remote_tags(repo)?
+        .into_iter()
+        .filter_map(|tag| version_from_tag(&tag).map(|v| (tag, v)))
+        .collect::<Vec<_>>();
+    let best = req
+        .best_match(tags.iter().map(|(_, v)| v))
+        .ok_or(anyhow!("No version tag matches `{}`", req))?;
+    // Prefer `v1.2.3` over `1.2.3` if both exist, for determinism
+    let (tag, version) = tags
+        .iter()
+        .filter(|(_, v)| v == best)
+        .max_by_key(|(tag, _)| tag.starts_with('v'))
+        .unwrap();
+    Ok((tag.clone(), *version))
+}
+
+/// Find the packages in `repo_dir` and pick the ones to install.
+fn select_packages(repo_dir: &Path, all: bool, prompter: &dyn Prompter) -> Result<Vec<PathBuf>> {
+    let package_dirs = find_packages(repo_dir);
+    if package_dirs.len() <= 1 || all {
+        if package_dirs.is_empty() {
+            bail!("No `typst.toml` found in the repository");
+        }
+        return Ok(package_dirs);
+    }
+
+    let items = package_dirs
+        .iter()
+        .map(|dir| {
+            let rel_path = dir.strip_prefix(repo_dir).unwrap_or(dir);
+            match read_manifest(dir) {
+                Ok(m) => format!(
+                    "{}:{} ({})",
+                    m.package.name,
+                    m.package.version,
+                    rel_path.display()
+                ),
+                Err(_) => format!("{} (invalid manifest)", rel_path.display()),
+            }
+        })
+        .collect::<Vec<_>>();
+    let selected = prompter.multi_select(
+        "Multiple packages found. Choose the ones to install",
+        &items.iter().map(String::as_str).collect::<Vec<_>>(),
+    )?;
+    if selected.is_empty() {
+        bail!(
+            "No package selected. Pass `--path <SUBDIR>` or `--all`. Packages found:\n{}",
+            items
+                .iter()
+                .map(|i| format!("\t{}", i))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+    Ok(selected
+        .into_iter()
+        .map(|i| package_dirs[i].clone())
+        .collect())
+}
@@ -1,6 +1,5 @@
 use anyhow::{anyhow, bail, Result};
 use clap::Parser;
-use dialoguer::{Confirm, Input, MultiSelect};
 use log::info;
 use regex::Regex;
 use std::{
@@ -16,6 +15,7 @@ use url::Url;
 
 use crate::{
     model::{CATEGORIES, DISCIPLINES},
+    prompt::{Prompter, Text},
     utils::{read_manifest, write_manifest},
 };
 
@@ -34,14 +34,9 @@ fn entrypoint_validator(input: &String) -> std::result::Result<(), anyhow::Error
     Ok(())
 }
 
-pub fn init(package_dir: &Path, args: &InitArgs) -> Result<()> {
-    if read_manifest(package_dir).is_ok()
-        && !Confirm::new()
-            .with_prompt("A package manifest already exists. Overwrite?")
-            .default(false)
-            .interact()?
-    {
-        bail!("Aborted");
+pub fn init(package_dir: &Path, args: &InitArgs, prompter: &dyn Prompter) -> Result<()> {
+    if read_manifest(package_dir).is_ok() {
+        prompter.proceed("A package manifest already exists. Overwrite?")?;
     }
 
     info!("Initializing a new package...");
@@ -54,8 +49,8 @@ pub fn init(package_dir: &Path, args: &InitArgs) -> Result<()> {
         println!("Package name: {}", name);
         name.to_string()
     } else {
-        let name = Input::new()
-            .with_prompt("Enter the package name")
+        let name = Text::new("Enter the package name")
+            .hint("Pass the package name as an argument: `typship init <NAME>`.")
             .validate_with(|input: &String| -> Result<()> {
                 if name_re.is_match(input) {
                     Ok(())
@@ -64,75 +59,62 @@ pub fn init(package_dir: &Path, args: &InitArgs) -> Result<()> {
                 }
             });
         let name = if let Some(default_name) =
-            fs::canonicalize(".")?.file_name().and_then(|s| s.to_str())
+            fs::canonicalize(package_dir)?.file_name().and_then(|s| s.to_str())
         {
-            name.default(default_name.into())
+            name.default(default_name)
         } else {
             name
         };
-        name.interact_text()?
+        name.interact(prompter)?
     };
 
-    let author: String = Input::new()
-        .with_prompt("Enter the package author")
+    let author: String = Text::new("Enter the package author")
         .default(whoami::username())
-        .interact_text()?;
+        .interact(prompter)?;
 
-    let version: String = Input::new()
-        .with_prompt("Enter the package version")
-        .default("0.1.0".into())
+    let version: String = Text::new("Enter the package version")
+        .default("0.1.0")
         .validate_with(|input: &String| -> Result<()> {
             PackageVersion::from_str(input)
                 .map(|_| ())
                 .map_err(|msg| anyhow!(msg))
         })
-        .interact_text()?;
+        .interact(prompter)?;
     let version = PackageVersion::from_str(&version).unwrap();
 
-    let categories = MultiSelect::new()
-        .with_prompt("Choose the package category")
-        .items(&CATEGORIES)
-        .interact()
-        .unwrap()
+    let categories = prompter
+        .multi_select("Choose the package category", &CATEGORIES)?
         .into_iter()
         .map(|i| CATEGORIES[i].into())
         .collect();
 
-    let disciplines = MultiSelect::new()
-        .with_prompt("Choose the package discipline")
-        .items(&DISCIPLINES)
-        .interact()
-        .unwrap()
+    let disciplines = prompter
+        .multi_select("Choose the package discipline", &DISCIPLINES)?
         .into_iter()
         .map(|i| DISCIPLINES[i].into())
         .collect();
 
-    let entrypoint: String = Input::new()
-        .with_prompt("Enter the package entrypoint")
+    let entrypoint: String = Text::new("Enter the package entrypoint")
         .default(
             Path::new("src")
                 .join(Path::new("lib.typ"))
-                .to_string_lossy()
-                .into(),
+                .to_string_lossy(),
         )
         .validate_with(entrypoint_validator)
-        .interact_text()?;
+        .interact(prompter)?;
 
-    let description: String = Input::new()
-        .with_prompt("Enter the package description")
+    let description: String = Text::new("Enter the package description")
         .allow_empty(true)
-        .interact_text()?;
+        .interact(prompter)?;
 
-    let keywords: String = Input::new()
-        .with_prompt("Enter the package keywords(separated by comma)")
+    let keywords: String = Text::new("Enter the package keywords(separated by comma)")
         .allow_empty(true)
-        .interact_text()?;
+        .interact(prompter)?;
     let keywords = keywords.split(',').map(|s| s.trim().into()).collect();
 
-    let homepage: String = Input::new()
-        .with_prompt("Enter the package homepage URL")
+    let homepage: String = Text::new("Enter the package homepage URL")
         .allow_empty(true)
-        .default("".into())
+        .default("")
         .validate_with(|input: &String| -> Result<()> {
             if input.is_empty() {
                 Ok(())
@@ -145,17 +127,16 @@ pub fn init(package_dir: &Path, args: &InitArgs) -> Result<()> {
                 }
             }
         })
-        .interact_text()?;
+        .interact(prompter)?;
     let homepage = if homepage.is_empty() {
         None
     } else {
         Some(homepage.into())
     };
 
-    let repository: String = Input::new()
-        .with_prompt("Enter the package repository URL")
+    let repository: String = Text::new("Enter the package repository URL")
         .allow_empty(true)
-        .default("".into())
+        .default("")
         .validate_with(|input: &String| -> Result<()> {
             if input.is_empty() {
                 Ok(())
@@ -168,17 +149,16 @@ pub fn init(package_dir: &Path, args: &InitArgs) -> Result<()> {
                 }
             }
         })
-        .interact_text()?;
+        .interact(prompter)?;
     let repository = if repository.is_empty() {
         None
     } else {
         Some(repository.into())
     };
 
-    let compiler: String = Input::new()
-        .with_prompt("Enter compiler version")
+    let compiler: String = Text::new("Enter compiler version")
         .allow_empty(true)
-        .default("".into())
+        .default("")
         .validate_with(|input: &String| -> Result<()> {
             if input.is_empty() {
                 Ok(())
@@ -188,7 +168,7 @@ pub fn init(package_dir: &Path, args: &InitArgs) -> Result<()> {
                     .map_err(|msg| anyhow!(msg))
             }
         })
-        .interact_text()?;
+        .interact(prompter)?;
     let compiler = VersionBound::from_str(&compiler).ok();
 
     let package_info = PackageInfo {
@@ -209,28 +189,22 @@ pub fn init(package_dir: &Path, args: &InitArgs) -> Result<()> {
         license: None,
     };
 
-    let has_template = Confirm::new()
-        .with_prompt("Does the package have a template?")
-        .default(false)
-        .interact()?;
+    let has_template = prompter.confirm("Does the package have a template?", false)?;
 
     let template = if has_template {
-        let path: String = Input::new()
-            .with_prompt("Enter the template project path")
-            .default("template".into())
+        let path: String = Text::new("Enter the template project path")
+            .default("template")
             .allow_empty(false)
-            .interact_text()?;
+            .interact(prompter)?;
 
-        let entrypoint: String = Input::new()
-            .with_prompt("Enter the template entrypoint")
+        let entrypoint: String = Text::new("Enter the template entrypoint")
             .default(format!("{}/main.typ", path))
             .validate_with(entrypoint_validator)
-            .interact_text()?;
+            .interact(prompter)?;
 
-        let thumbnail: String = Input::new()
-            .with_prompt("Enter the template thumbnail path(optional)")
+        let thumbnail: String = Text::new("Enter the template thumbnail path(optional)")
             .allow_empty(true)
-            .interact_text()?;
+            .interact(prompter)?;
 
         let path = path.into();
         let entrypoint = entrypoint.into();
@@ -260,7 +234,7 @@ pub fn init(package_dir: &Path, args: &InitArgs) -> Result<()> {
 
     // TODO: generate other files: entrypoint, readme(ask) ...
 
-    let entrypoint = Path::new(manifest.package.entrypoint.as_ref());
+    let entrypoint = package_dir.join(manifest.package.entrypoint.as_str());
     if let Some(parent) = entrypoint.parent() {
         fs::create_dir_all(parent)?;
     }
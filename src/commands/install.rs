@@ -1,39 +1,212 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use anyhow::{bail, Result};
-use clap::Parser;
-use dialoguer::Confirm;
-use log::{debug, warn};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use ignore::WalkBuilder;
+use log::{debug, info, warn};
+use regex::Regex;
+use typst_syntax::is_ident;
+use typst_syntax::package::{PackageManifest, PackageSpec, PackageVersion};
 
+use crate::model::receipt::{InstallKind, Receipt, Source};
+use crate::prompt::Prompter;
 use crate::utils::walkers::walker_install;
-use crate::utils::{read_manifest, typst_local_dir};
+use crate::utils::{
+    package_root, read_manifest, sha256_file, symlink_dir, typst_local_dir, unpack_archive,
+    write_receipt,
+};
 
-const LONG_ABOUT: &str =
-    "Install the package to a certain namespace. Must be in the package directory.";
+const LONG_ABOUT: &str = "Install a package to a certain namespace. By default, the package in the current directory is installed; another package directory or a `.tar.gz` package bundle (as served by packages.typst.org) may be given instead.";
 
 #[derive(Parser)]
 #[command(long_about = LONG_ABOUT)]
-/// Install the current package to a certain namespace
+/// Install a package to a certain namespace
 pub struct InstallArgs {
     #[arg(
         long_help = "The target namespace to install the package. Please avoid using `preview`."
     )]
     /// The target namespace to install the package
     pub target: String,
+
+    /// Package directory or `.tar.gz` bundle to install (defaults to the
+    /// current directory)
+    pub source: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value = "copy")]
+    #[arg(
+        long_help = "How to place the package: copy (a snapshot of the files); symlink (a link to the package directory, like `typship dev` for any namespace); hardlink (a snapshot sharing disk space with the package directory, falls back to copy across file systems)."
+    )]
+    /// How to place the package: copy, symlink, or hardlink
+    pub link: LinkMode,
+
+    #[arg(long = "as", value_name = "NAME:VERSION")]
+    #[arg(
+        long_help = "Install the package under a different name and/or version, e.g. to test a release candidate side by side with the stable version. The installed `typst.toml` and the imports of the package itself in the template are rewritten accordingly."
+    )]
+    /// Install under a different name and version
+    pub rename: Option<InstallAs>,
+}
+
+/// `<name>:<version>` to install a package under.
+#[derive(Debug, Clone)]
+pub struct InstallAs {
+    pub name: String,
+    pub version: PackageVersion,
+}
+
+impl FromStr for InstallAs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((name, version)) = s.split_once(':') else {
+            bail!("Expected `<name>:<version>`");
+        };
+        if !is_ident(name) {
+            bail!("`{}` is not a valid package name", name);
+        }
+        Ok(Self {
+            name: name.into(),
+            version: PackageVersion::from_str(version).map_err(|e| anyhow!(e))?,
+        })
+    }
+}
+
+/// Where and how [`install_from`] places a package.
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    /// The target namespace, without the `@` prefix
+    pub namespace: String,
+    pub link: LinkMode,
+    /// Install under a different name and version
+    pub rename: Option<InstallAs>,
 }
 
-pub fn install(src_dir: &Path, args: &InstallArgs) -> Result<()> {
-    let mut target = args.target.to_string();
+impl InstallOptions {
+    pub fn new(namespace: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            link: LinkMode::Copy,
+            rename: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LinkMode {
+    Copy,
+    Symlink,
+    Hardlink,
+}
+
+impl From<LinkMode> for InstallKind {
+    fn from(value: LinkMode) -> Self {
+        match value {
+            LinkMode::Copy => InstallKind::Copy,
+            LinkMode::Symlink => InstallKind::Symlink,
+            LinkMode::Hardlink => InstallKind::Hardlink,
+        }
+    }
+}
+
+pub fn install(current_dir: &Path, args: &InstallArgs, prompter: &dyn Prompter) -> Result<()> {
+    let options = InstallOptions {
+        namespace: args.target.clone(),
+        link: args.link,
+        rename: args.rename.clone(),
+    };
+    let src = match &args.source {
+        Some(path) => current_dir.join(path),
+        None => current_dir.to_path_buf(),
+    };
+    if !src.is_file() {
+        let source = Source::Path {
+            path: fs::canonicalize(&src)
+                .with_context(|| format!("Package directory {} not found", src.display()))?,
+        };
+        return install_from(&typst_local_dir(), &src, &options, source, prompter);
+    }
+
+    install_archive(&typst_local_dir(), &src, &options, prompter)
+}
+
+/// Install the `.tar.gz` package bundle at `archive` into `packages_dir`.
+pub fn install_archive(
+    packages_dir: &Path,
+    archive: &Path,
+    options: &InstallOptions,
+    prompter: &dyn Prompter,
+) -> Result<()> {
+    if options.link == LinkMode::Symlink {
+        bail!("An archive cannot be installed as a symlink");
+    }
+    let temp_dir = tempfile::tempdir()?;
+    unpack_archive(
+        fs::File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?,
+        temp_dir.path(),
+    )
+    .with_context(|| format!("Failed to read {}", archive.display()))?;
+    let package_dir = package_root(temp_dir.path())?;
+    validate_package(&package_dir)?;
+    let source = Source::Archive {
+        path: fs::canonicalize(archive)?,
+    };
+    install_from(packages_dir, &package_dir, options, source, prompter)
+}
+
+/// Check that the manifest in `package_dir` is valid and that the files it
+/// refers to exist.
+pub fn validate_package(package_dir: &Path) -> Result<PackageManifest> {
+    let manifest = read_manifest(package_dir)?;
+    if !is_ident(&manifest.package.name) {
+        bail!("`{}` is not a valid package name", manifest.package.name);
+    }
+    if !package_dir
+        .join(manifest.package.entrypoint.as_str())
+        .is_file()
+    {
+        bail!(
+            "The entrypoint `{}` of `{}:{}` does not exist",
+            manifest.package.entrypoint,
+            manifest.package.name,
+            manifest.package.version
+        );
+    }
+    if let Some(template) = &manifest.template {
+        let template_dir = package_dir.join(template.path.as_str());
+        if !template_dir.is_dir() {
+            bail!("The template directory `{}` does not exist", template.path);
+        }
+        if !template_dir.join(template.entrypoint.as_str()).is_file() {
+            bail!(
+                "The template entrypoint `{}` does not exist",
+                template.entrypoint
+            );
+        }
+    }
+    Ok(manifest)
+}
+
+/// Install the package in `src_dir` into `packages_dir` as described by
+/// `options`, recording `source` in its receipt.
+pub fn install_from(
+    packages_dir: &Path,
+    src_dir: &Path,
+    options: &InstallOptions,
+    source: Source,
+    prompter: &dyn Prompter,
+) -> Result<()> {
+    let link = options.link;
+    let mut target = options.namespace.clone();
     while target.starts_with('@') {
-        if !Confirm::new()
-            .with_prompt(format!(
+        if prompter.confirm(
+            &format!(
                 "Namespace parameter should not contain `@` prefix. Do you mean `{}`?",
                 &target[1..]
-            ))
-            .default(true)
-            .interact()?
-        {
+            ),
+            true,
+        )? {
             target = target[1..].to_string();
         } else {
             bail!("Aborted");
@@ -41,57 +214,188 @@ pub fn install(src_dir: &Path, args: &InstallArgs) -> Result<()> {
     }
 
     let current = read_manifest(src_dir)?;
+    if options.rename.is_some() && link == LinkMode::Symlink {
+        bail!("A package cannot be installed as a symlink under a different name or version");
+    }
 
     if target.as_str() == "preview" {
         // TODO: recommend `typship dev`(symlink) after finishing the dev command
         warn!(
             "Installing directly to `preview` is discouraged, since it might break the versioning."
         );
-        if !Confirm::new()
-            .with_prompt("Are you sure you want to install directly to `preview`?")
-            .default(false)
-            .interact()?
-        {
-            bail!("Aborted");
-        }
+        prompter.proceed("Are you sure you want to install directly to `preview`?")?;
     }
 
-    let namespace_dir = typst_local_dir().join(&target);
-    let package_dir = namespace_dir.join(current.package.name.as_str());
+    let spec = match &options.rename {
+        Some(rename) => PackageSpec {
+            namespace: target.as_str().into(),
+            name: rename.name.as_str().into(),
+            version: rename.version,
+        },
+        None => PackageSpec {
+            namespace: target.as_str().into(),
+            name: current.package.name.clone(),
+            version: current.package.version,
+        },
+    };
+    let package_dir = packages_dir.join(&target).join(spec.name.as_str());
 
-    let version_dir = package_dir.join(current.package.version.to_string());
-    if !version_dir.exists() {
-        std::fs::create_dir_all(&version_dir)?;
+    let version = spec.version.to_string();
+    let version_dir = package_dir.join(&version);
+    if version_dir.is_symlink()
+        || version_dir
+            .read_dir()
+            .is_ok_and(|mut entries| entries.next().is_some())
+    {
+        prompter.proceed(&format!("`{}` already exists. Overwrite?", spec))?;
     }
-    if version_dir.read_dir()?.next().is_some() {
-        if !Confirm::new()
-            .with_prompt(format!(
-                "`@{}/{}:{}` already exists. Overwrite?",
-                target, current.package.name, current.package.version
-            ))
-            .default(false)
-            .interact()?
-        {
-            bail!("Aborted")
-        } else {
-            std::fs::remove_dir_all(&version_dir)?;
-            std::fs::create_dir_all(&version_dir)?;
+    fs::create_dir_all(&package_dir)?;
+
+    // The package is prepared in a staging directory next to the target
+    // first, so that a failure midway never leaves a half-installed package
+    // behind.
+    let staging = tempfile::Builder::new()
+        .prefix(&format!(".{}-staging-", version))
+        .tempdir_in(&package_dir)
+        .context("Failed to create the staging directory")?;
+    let staged = staging.path().join(&version);
+    let mut receipt = Receipt::new(&spec, link.into(), source);
+    match link {
+        LinkMode::Symlink => {
+            let src_dir = fs::canonicalize(src_dir)?;
+            debug!("Linking {:?} to {:?}", staged, src_dir);
+            symlink_dir(&src_dir, &staged)?;
+        }
+        LinkMode::Copy | LinkMode::Hardlink => {
+            let mut hardlink = link == LinkMode::Hardlink;
+            for entry in (walker_install(src_dir)?).into_iter().flatten() {
+                let path = entry.path();
+                let rel_path = path.strip_prefix(src_dir).unwrap();
+                let dest = staged.join(rel_path);
+                if path.is_file() {
+                    if let Some(parent) = dest.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    if hardlink {
+                        debug!("Hard linking {:?} to {:?}", dest, path);
+                        if let Err(e) = fs::hard_link(path, &dest) {
+                            warn!("Failed to create hard links ({}), copying instead", e);
+                            hardlink = false;
+                            receipt.kind = InstallKind::Copy;
+                        }
+                    }
+                    if !hardlink {
+                        debug!("Copying {:?} to {:?}", path, dest);
+                        fs::copy(path, &dest)?;
+                    }
+                    receipt.files.insert(
+                        rel_path.to_string_lossy().replace('\\', "/"),
+                        sha256_file(&dest)?,
+                    );
+                } else if path.is_dir() {
+                    fs::create_dir_all(&dest)?;
+                }
+            }
+            if !staged.exists() {
+                fs::create_dir_all(&staged)?;
+            }
+            if options.rename.is_some() {
+                for rel_path in rewrite_self_references(&staged, &current, &spec)? {
+                    receipt.files.insert(
+                        rel_path.to_string_lossy().replace('\\', "/"),
+                        sha256_file(&staged.join(&rel_path))?,
+                    );
+                }
+            }
         }
     }
 
-    for entry in (walker_install(src_dir)?).into_iter().flatten() {
+    swap_in(&staged, &version_dir)?;
+    write_receipt(packages_dir, &receipt)?;
+    info!("Installed `{}`", spec);
+    Ok(())
+}
+
+/// Point the package in `dir`, installed from a package described by
+/// `original`, to `spec`: the manifest gets the new name and version, and
+/// imports of the original package in the template import `spec` instead.
+/// Returns the paths of the rewritten files, relative to `dir`.
+fn rewrite_self_references(
+    dir: &Path,
+    original: &PackageManifest,
+    spec: &PackageSpec,
+) -> Result<Vec<PathBuf>> {
+    let mut rewritten = vec![PathBuf::from("typst.toml")];
+    let manifest_path = dir.join("typst.toml");
+    let mut manifest: toml::Table = fs::read_to_string(&manifest_path)?.parse()?;
+    let package = manifest
+        .get_mut("package")
+        .and_then(|p| p.as_table_mut())
+        .ok_or(anyhow!("Missing `[package]` in the manifest"))?;
+    package.insert("name".into(), spec.name.to_string().into());
+    package.insert("version".into(), spec.version.to_string().into());
+    replace_file(&manifest_path, &toml::to_string_pretty(&manifest)?)?;
+
+    let Some(template) = &original.template else {
+        return Ok(rewritten);
+    };
+    let import_re = Regex::new(&format!(
+        r#""@[a-zA-Z_][a-zA-Z0-9_-]*/{}:{}""#,
+        regex::escape(&original.package.name),
+        regex::escape(&original.package.version.to_string())
+    ))?;
+    let replacement = format!("\"{}\"", spec);
+    let template_dir = dir.join(template.path.as_str());
+    for entry in WalkBuilder::new(&template_dir)
+        .standard_filters(false)
+        .build()
+        .flatten()
+    {
         let path = entry.path();
-        let dest = version_dir.join(path.strip_prefix(src_dir).unwrap());
-        debug!("Copying {:?} to {:?}", path, dest);
-        if path.is_file() {
-            if let Some(parent) = dest.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            fs::copy(path, &dest)?;
-        } else if path.is_dir() {
-            fs::create_dir_all(&dest)?;
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "typ") {
+            continue;
+        }
+        let content = fs::read_to_string(path)?;
+        let updated = import_re.replace_all(&content, regex::NoExpand(&replacement));
+        if updated != content {
+            debug!("Rewriting self-imports in {:?}", path);
+            replace_file(path, &updated)?;
+            rewritten.push(path.strip_prefix(dir).unwrap().to_path_buf());
         }
     }
+    Ok(rewritten)
+}
+
+/// Write `contents` to a new file at `path`, so that hard links to the old
+/// file are left untouched.
+fn replace_file(path: &Path, contents: &str) -> Result<()> {
+    fs::remove_file(path)?;
+    fs::write(path, contents)?;
+    Ok(())
+}
 
+/// Move `staged` to `target`, replacing whatever is there. The previous
+/// content is only removed once the new one is in place, and is restored if
+/// the swap fails.
+fn swap_in(staged: &Path, target: &Path) -> Result<()> {
+    let Some(parent) = target.parent() else {
+        bail!("Invalid install target `{}`", target.display());
+    };
+    if !target.exists() && !target.is_symlink() {
+        fs::rename(staged, target)?;
+        return Ok(());
+    }
+
+    // Dropping the backup directory removes the old content (without
+    // following symlinks).
+    let backup = tempfile::Builder::new()
+        .prefix(".typship-old-")
+        .tempdir_in(parent)?;
+    let old = backup.path().join("old");
+    fs::rename(target, &old).context("Failed to move the existing version aside")?;
+    if let Err(e) = fs::rename(staged, target) {
+        fs::rename(&old, target).context("Failed to restore the previous version")?;
+        return Err(e).context("Failed to move the new version into place");
+    }
     Ok(())
 }
@@ -0,0 +1,216 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Result;
+use clap::Parser;
+use log::{debug, info};
+use serde::Serialize;
+use typst_syntax::package::{PackageSpec, PackageVersion};
+
+use crate::model::receipt::{InstallKind, Receipt};
+use crate::utils::{
+    dir_size, format_size, format_timestamp, package_file_hashes, read_receipt, typst_local_dir,
+};
+
+const LONG_ABOUT: &str = "List the packages in the data directory across all namespaces. Dev symlinks, symlinks and copies installed by typship, copies modified since, and entries typship did not create are marked as such.";
+
+#[derive(Parser)]
+#[command(long_about = LONG_ABOUT)]
+/// List the packages in the data directory
+pub struct ListArgs {
+    #[arg(short, long)]
+    /// Only list packages in this namespace (without the `@` prefix)
+    pub namespace: Option<String>,
+
+    #[arg(long)]
+    /// Print the list as JSON
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryStatus {
+    /// Symlink created by `dev`
+    Dev,
+    /// Symlink created by `install --link symlink`
+    Linked,
+    /// Copy (or hard links) created by `install` or `download`
+    Installed,
+    /// Like [`EntryStatus::Installed`], but the files differ from the ones
+    /// recorded in the receipt
+    Modified,
+    /// Not created by typship
+    Unmanaged,
+}
+
+impl EntryStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            EntryStatus::Dev => "dev",
+            EntryStatus::Linked => "linked",
+            EntryStatus::Installed => "installed",
+            EntryStatus::Modified => "modified",
+            EntryStatus::Unmanaged => "unmanaged",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListEntry {
+    pub namespace: String,
+    pub name: String,
+    pub version: PackageVersion,
+    pub status: EntryStatus,
+    pub path: PathBuf,
+    /// Where the entry points to, if it is a symlink
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+    /// Size in bytes, not counted for symlinks
+    pub size: Option<u64>,
+    pub receipt: Option<Receipt>,
+}
+
+impl ListEntry {
+    pub fn spec(&self) -> PackageSpec {
+        PackageSpec {
+            namespace: self.namespace.as_str().into(),
+            name: self.name.as_str().into(),
+            version: self.version,
+        }
+    }
+}
+
+pub fn list(args: &ListArgs) -> Result<()> {
+    let entries = scan(&typst_local_dir(), args.namespace.as_deref())?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        info!("No packages found");
+        return Ok(());
+    }
+
+    let specs = entries
+        .iter()
+        .map(|e| e.spec().to_string())
+        .collect::<Vec<_>>();
+    let width = specs.iter().map(|s| s.len()).max().unwrap_or_default();
+    for (entry, spec) in entries.iter().zip(specs) {
+        let size = entry.size.map(format_size).unwrap_or("-".into());
+        let installed_at = entry
+            .receipt
+            .as_ref()
+            .map(|r| format_timestamp(r.installed_at))
+            .unwrap_or_default();
+        let origin = match (&entry.link_target, &entry.receipt) {
+            (Some(target), _) => format!("-> {}", target.display()),
+            (None, Some(receipt)) => format!("from {}", receipt.source),
+            (None, None) => String::new(),
+        };
+        let line = format!(
+            "{:width$}  {:9}  {:>10}  {:16}  {}",
+            spec,
+            entry.status.as_str(),
+            size,
+            installed_at,
+            origin,
+        );
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+/// Collect every package version in `packages_dir`, optionally limited to one
+/// namespace.
+pub fn scan(packages_dir: &Path, namespace: Option<&str>) -> Result<Vec<ListEntry>> {
+    let mut entries = vec![];
+    if !packages_dir.is_dir() {
+        return Ok(entries);
+    }
+    for ns in sorted_dirs(packages_dir)? {
+        if namespace.is_some_and(|n| n != ns) {
+            continue;
+        }
+        for name in sorted_dirs(&packages_dir.join(&ns))? {
+            let package_dir = packages_dir.join(&ns).join(&name);
+            let mut versions = vec![];
+            for entry in package_dir.read_dir()? {
+                let entry = entry?;
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                match PackageVersion::from_str(&file_name) {
+                    Ok(version) => versions.push((version, entry.path())),
+                    Err(_) => debug!("Skipping {}", entry.path().display()),
+                }
+            }
+            versions.sort_by_key(|(version, _)| *version);
+
+            for (version, path) in versions {
+                let spec = PackageSpec {
+                    namespace: ns.as_str().into(),
+                    name: name.as_str().into(),
+                    version,
+                };
+                entries.push(inspect(packages_dir, &spec, path)?);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn inspect(packages_dir: &Path, spec: &PackageSpec, path: PathBuf) -> Result<ListEntry> {
+    let receipt = read_receipt(packages_dir, spec)?;
+    let link_target = if path.is_symlink() {
+        Some(fs::read_link(&path)?)
+    } else {
+        None
+    };
+    let is_link = link_target.is_some();
+    let mut status = match receipt.as_ref().map(|r| r.kind) {
+        Some(InstallKind::Dev) if is_link => EntryStatus::Dev,
+        Some(InstallKind::Symlink) if is_link => EntryStatus::Linked,
+        Some(InstallKind::Copy | InstallKind::Hardlink) if !is_link => EntryStatus::Installed,
+        _ => EntryStatus::Unmanaged,
+    };
+    if let Some(receipt) = receipt
+        .as_ref()
+        .filter(|_| status == EntryStatus::Installed)
+    {
+        // Receipts of older versions have no hashes to compare with
+        let files = &receipt.files;
+        if !files.is_empty() && package_file_hashes(&path).ok().as_ref() != Some(files) {
+            status = EntryStatus::Modified;
+        }
+    }
+    let receipt = receipt.filter(|_| status != EntryStatus::Unmanaged);
+    let size = if link_target.is_none() && path.is_dir() {
+        Some(dir_size(&path)?)
+    } else {
+        None
+    };
+    Ok(ListEntry {
+        namespace: spec.namespace.to_string(),
+        name: spec.name.to_string(),
+        version: spec.version,
+        status,
+        path,
+        link_target,
+        size,
+        receipt,
+    })
+}
+
+/// Names of the visible subdirectories of `dir`, sorted.
+fn sorted_dirs(dir: &Path) -> Result<Vec<String>> {
+    let mut dirs = vec![];
+    for entry in dir.read_dir()? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with('.') && entry.path().is_dir() {
+            dirs.push(name);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
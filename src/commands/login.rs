@@ -1,6 +1,7 @@
 use anyhow::Result;
 use clap::Parser;
 
+use crate::prompt::Prompter;
 use crate::regs::universe;
 
 const LONG_ABOUT: &str = "Login to the certain registry. Currently, only the official Universe (GitHub) registry is supported.";
@@ -15,9 +16,9 @@ pub struct LoginArgs {
     pub registry: String,
 }
 
-pub fn login(args: &LoginArgs) -> Result<()> {
+pub fn login(args: &LoginArgs, prompter: &dyn Prompter) -> Result<()> {
     match args.registry.as_str() {
-        "universe" => universe::login(),
+        "universe" => universe::login(prompter),
         _ => {
             anyhow::bail!("Unsupported registry: {}", args.registry);
         }
@@ -7,8 +7,10 @@ pub mod download;
 pub mod exclude;
 pub mod init;
 pub mod install;
+pub mod list;
 pub mod login;
 pub mod publish;
+pub mod uninstall;
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -19,6 +21,8 @@ pub enum Commands {
     Exclude(exclude::ExcludeArgs),
     Init(init::InitArgs),
     Install(install::InstallArgs),
+    List(list::ListArgs),
     Login(login::LoginArgs),
     Publish(publish::PublishArgs),
+    Uninstall(uninstall::UninstallArgs),
 }
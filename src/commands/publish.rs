@@ -1,9 +1,13 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::{ArgAction, Parser};
+use log::{info, warn};
 
-use crate::regs::universe::{self, UploadMethod};
+use crate::commands::check::run_checks;
+use crate::config::CONFIG;
+use crate::prompt::Prompter;
+use crate::regs::universe::{self, PublishOptions, UploadMethod, Upstream};
 use crate::utils::read_manifest;
 
 const LONG_ABOUT: &str =
@@ -25,17 +29,74 @@ pub struct PublishArgs {
 
     #[arg(long, value_enum, default_value = "sparse")]
     #[arg(
-        long_help = "Upload method: sparse (uses git sparse-checkout); api (uploads files one by one, for legacy git, slower)."
+        long_help = "Upload method: sparse (uses git sparse-checkout); api (uploads files one by one, for legacy git, slower); git-data (uploads all files in a single commit through the GitHub Git Data API, without git, also for large files)."
     )]
-    /// Upload method: sparse (uses git sparse-checkout); api (uploads files one by one, for legacy git, slower).
+    /// Upload method: sparse (uses git sparse-checkout); api (uploads files one by one, for legacy git, slower); git-data (single commit via the GitHub API).
     pub upload_method: UploadMethod,
+
+    #[arg(long)]
+    /// Keep the sparse checkout in the temp directory, for debugging
+    pub keep: bool,
+
+    #[arg(long, value_name = "OWNER/REPO")]
+    #[arg(
+        long_help = "The fork of the packages repository to submit from, as `owner/repo` or `repo` (of your account). Defaults to `fork` in `[tool.typship]` of `typst.toml`, then `fork` in `[universe]` of the typship config; otherwise you are asked."
+    )]
+    /// The fork of the packages repository to submit from
+    pub fork: Option<String>,
+
+    #[arg(long, value_name = "FILE")]
+    #[arg(
+        long_help = "Read the GitHub token from a file. Otherwise, the token is taken from $TYPSHIP_UNIVERSE_TOKEN, $GITHUB_TOKEN, the `credential_helper` command in `[tokens]` of the typship config (git credential protocol), or the token saved by `typship login universe`, in this order."
+    )]
+    /// Read the GitHub token from a file
+    pub token_file: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    #[arg(
+        long_help = "Use the contents of a file as the PR description. Otherwise, the description is generated from `typst.toml` (and the CHANGELOG section of the version, for updates), you are asked about each item of the submission checklist, and $EDITOR is opened to edit the result."
+    )]
+    /// Use the contents of a file as the PR description
+    pub message_file: Option<PathBuf>,
+
+    #[arg(long)]
+    #[arg(
+        long_help = "Publish even if `typship check` finds errors in the package. By default, publishing is aborted before any branch is created."
+    )]
+    /// Publish even if `typship check` finds errors
+    pub no_verify: bool,
 }
 
-pub async fn publish(package_dir: &Path, args: &PublishArgs) -> Result<()> {
+pub async fn publish(
+    package_dir: &Path,
+    args: &PublishArgs,
+    prompter: &dyn Prompter,
+) -> Result<()> {
     let current = read_manifest(package_dir)?;
+    if args.no_verify {
+        warn!("Skipping the package checks (--no-verify)");
+    } else {
+        info!("Checking the package...");
+        let errors = run_checks(package_dir)?;
+        if errors > 0 {
+            bail!(
+                "Found {} error(s) in the package. Fix them, or pass `--no-verify` to publish anyway.",
+                errors
+            );
+        }
+    }
     match args.registry.as_str() {
         "universe" => {
-            universe::publish(&current, package_dir, args.dry_run, args.upload_method).await?
+            let options = PublishOptions {
+                dry_run: args.dry_run,
+                upload_method: args.upload_method,
+                keep: args.keep,
+                fork: args.fork.clone(),
+                token_file: args.token_file.clone(),
+                message_file: args.message_file.clone(),
+                upstream: Upstream::from_config(&CONFIG.try_lock()?.universe),
+            };
+            universe::publish(&current, package_dir, &options, prompter).await?
         }
         _ => {
             anyhow::bail!("Unsupported registry: {}", args.registry);
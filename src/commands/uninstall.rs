@@ -0,0 +1,149 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use log::{info, warn};
+use typst_syntax::package::{PackageVersion, VersionlessPackageSpec};
+
+use crate::commands::list::{scan, EntryStatus, ListEntry};
+use crate::prompt::Prompter;
+use crate::utils::{receipts_dir, remove_receipt, typst_local_dir};
+
+const LONG_ABOUT: &str = "Uninstall package versions from a namespace in the data directory. Specify a version (`@local/foo:0.2.0`), or all versions (`@local/foo` or `@local/foo:*`). Empty package and namespace directories are removed afterwards. Symlinks (created by `typship dev` or `typship install --link symlink`) are only removed with `--links`.";
+
+#[derive(Parser)]
+#[command(long_about = LONG_ABOUT)]
+/// Uninstall package versions from a namespace
+pub struct UninstallArgs {
+    /// The package to uninstall, e.g. `@local/foo:0.2.0`, `@local/foo` or
+    /// `@team/foo:*`
+    pub package: PackageMatcher,
+
+    #[arg(long)]
+    /// Also remove symlinks, such as the ones created by `typship dev`
+    pub links: bool,
+}
+
+/// A package in a namespace, with a specific version or any version.
+#[derive(Debug, Clone)]
+pub struct PackageMatcher {
+    pub package: VersionlessPackageSpec,
+    /// `None` matches every version
+    pub version: Option<PackageVersion>,
+}
+
+impl PackageMatcher {
+    pub fn matches(&self, entry: &ListEntry) -> bool {
+        entry.namespace == self.package.namespace
+            && entry.name == self.package.name
+            && self.version.is_none_or(|v| v == entry.version)
+    }
+}
+
+impl FromStr for PackageMatcher {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (package, version) = match s.split_once(':') {
+            Some((package, "*")) => (package, None),
+            Some((package, version)) => (
+                package,
+                Some(PackageVersion::from_str(version).map_err(|e| anyhow!(e))?),
+            ),
+            None => (s, None),
+        };
+        Ok(Self {
+            package: VersionlessPackageSpec::from_str(package).map_err(|e| anyhow!(e))?,
+            version,
+        })
+    }
+}
+
+impl fmt::Display for PackageMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(version) => write!(f, "{}:{}", self.package, version),
+            None => write!(f, "{}:*", self.package),
+        }
+    }
+}
+
+pub fn uninstall(args: &UninstallArgs, prompter: &dyn Prompter) -> Result<()> {
+    uninstall_from(&typst_local_dir(), &args.package, args.links, prompter)
+}
+
+/// Remove the package versions in `packages_dir` matching `matcher`. Returns
+/// an error if nothing matches.
+pub fn uninstall_from(
+    packages_dir: &Path,
+    matcher: &PackageMatcher,
+    links: bool,
+    prompter: &dyn Prompter,
+) -> Result<()> {
+    let mut entries = scan(packages_dir, Some(matcher.package.namespace.as_str()))?;
+    entries.retain(|e| matcher.matches(e));
+    if entries.is_empty() {
+        bail!("No installed package matches `{}`", matcher);
+    }
+
+    let (removable, skipped): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|e| links || e.link_target.is_none());
+    for entry in &skipped {
+        let kind = if entry.status == EntryStatus::Dev {
+            "dev symlink"
+        } else {
+            "symlink"
+        };
+        warn!(
+            "Skipping `{}`, which is a {}. Pass `--links` to remove it.",
+            entry.spec(),
+            kind
+        );
+    }
+    if removable.is_empty() {
+        bail!("Nothing to uninstall");
+    }
+
+    prompter.proceed(&format!(
+        "The following will be removed:\n{}\nContinue?",
+        removable
+            .iter()
+            .map(|e| format!("\t{} ({})", e.spec(), e.path.display()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    ))?;
+
+    for entry in &removable {
+        // `remove_dir_all` removes symlinks without following them
+        fs::remove_dir_all(&entry.path)?;
+        remove_receipt(packages_dir, &entry.spec())?;
+        info!("Removed `{}`", entry.spec());
+    }
+
+    let namespace = matcher.package.namespace.as_str();
+    let name = matcher.package.name.as_str();
+    remove_empty_dirs(&packages_dir.join(namespace).join(name), packages_dir)?;
+    let receipts = receipts_dir(packages_dir);
+    remove_empty_dirs(&receipts.join(namespace).join(name), &receipts)?;
+    Ok(())
+}
+
+/// Remove `dir` and its ancestors below `root`, as long as they are empty.
+fn remove_empty_dirs(dir: &Path, root: &Path) -> Result<()> {
+    let mut dir = dir;
+    while dir != root && dir.starts_with(root) {
+        if !dir.is_dir() || dir.read_dir()?.next().is_some() {
+            break;
+        }
+        fs::remove_dir(dir)?;
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => break,
+        }
+    }
+    Ok(())
+}
@@ -21,10 +21,40 @@ pub static CONFIG: LazyLock<Arc<Mutex<Config>>> = LazyLock::new(|| {
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Config {
     pub tokens: RegistryTokens,
+    #[serde(default)]
+    pub universe: UniverseConfig,
 }
 
 // TODO: use a enum or sth to manage
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct RegistryTokens {
     pub universe: Option<String>,
+    /// Command speaking the git credential protocol to get tokens from, e.g.
+    /// `gh auth git-credential`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_helper: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UniverseConfig {
+    /// The default fork to publish from, as `owner/repo` or `repo`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork: Option<String>,
+    /// Owner of the repository to submit to, instead of `typst`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Name of the repository to submit to, instead of `packages`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Base branch of submissions, instead of `main`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Base URL of the GitHub API, e.g. `https://github.example.com/api/v3`
+    /// for GitHub Enterprise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+    /// Base URL of the GitHub web and git server, derived from `api_url` by
+    /// default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_url: Option<String>,
 }
@@ -1,6 +1,7 @@
 pub mod commands;
 pub mod config;
 pub mod model;
+pub mod prompt;
 pub mod regs;
 pub mod utils;
 
@@ -1,10 +1,11 @@
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
 use commands::Commands;
 use log::error;
 
+use typship::prompt::{Interaction, Prompter, ScriptedPrompter, TerminalPrompter};
 use typship::{commands, ABOUT, NAME, VERSION};
 
 #[derive(Parser)]
@@ -14,6 +15,27 @@ use typship::{commands, ABOUT, NAME, VERSION};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[arg(short, long, global = true)]
+    #[arg(
+        long_help = "Approve every confirmation without asking, and take the default answer of other yes/no questions (e.g. optional steps stay skipped)."
+    )]
+    /// Approve every confirmation, take defaults for other questions
+    yes: bool,
+
+    #[arg(long, global = true)]
+    #[arg(
+        long_help = "Never prompt. Defaults are taken where possible; otherwise the command fails. Implied when there is no terminal."
+    )]
+    /// Never prompt, take defaults or fail
+    no_input: bool,
+
+    #[arg(long, global = true, value_name = "FILE")]
+    #[arg(
+        long_help = "Answer prompts from a TOML file mapping prompt texts to answers (booleans, strings, or lists of selected items). Prompts missing from the file are handled as without `--answers`: asked on the terminal if there is one, approved with `--yes`, and taking defaults or failing with `--no-input`."
+    )]
+    /// Answer prompts from a TOML file
+    answers: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -35,21 +57,36 @@ async fn main() {
     let cli = Cli::parse();
     let current_dir = std::env::current_dir().expect("Failed to get the current directory");
 
-    if let Err(e) = match_cmd(&current_dir, &cli).await {
+    let interaction = Interaction::detect(cli.yes, cli.no_input);
+    let prompter: Box<dyn Prompter> = match &cli.answers {
+        Some(path) => match ScriptedPrompter::from_file(path) {
+            Ok(prompter) => Box::new(prompter.fallback(interaction)),
+            Err(e) => {
+                error!("{:?}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(TerminalPrompter::new(interaction)),
+    };
+
+    if let Err(e) = match_cmd(&current_dir, &cli, prompter.as_ref()).await {
         error!("{:?}", e);
+        std::process::exit(1);
     }
 }
 
-async fn match_cmd(current_dir: &Path, args: &Cli) -> anyhow::Result<()> {
+async fn match_cmd(current_dir: &Path, args: &Cli, prompter: &dyn Prompter) -> anyhow::Result<()> {
     match &args.command {
         Commands::Check(_) => commands::check::check(current_dir),
         Commands::Clean(args) => commands::clean::clean(args),
         Commands::Dev(_) => commands::dev::dev(current_dir).await,
-        Commands::Download(args) => commands::download::download(args),
+        Commands::Download(args) => commands::download::download(args, prompter),
         Commands::Exclude(args) => commands::exclude::exclude(current_dir, args),
-        Commands::Init(args) => commands::init::init(current_dir, args),
-        Commands::Install(args) => commands::install::install(current_dir, args),
-        Commands::Login(args) => commands::login::login(args),
-        Commands::Publish(args) => commands::publish::publish(current_dir, args).await,
+        Commands::Init(args) => commands::init::init(current_dir, args, prompter),
+        Commands::Install(args) => commands::install::install(current_dir, args, prompter),
+        Commands::List(args) => commands::list::list(args),
+        Commands::Login(args) => commands::login::login(args, prompter),
+        Commands::Publish(args) => commands::publish::publish(current_dir, args, prompter).await,
+        Commands::Uninstall(args) => commands::uninstall::uninstall(args, prompter),
     }
 }
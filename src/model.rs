@@ -1,4 +1,7 @@
 pub mod manifest;
+pub mod package_list;
+pub mod receipt;
+pub mod version;
 
 pub const CATEGORIES: [&str; 19] = [
     "components",
@@ -25,8 +25,13 @@ impl UpdateTools<TypshipTools> for PackageManifest {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TypshipInfo {}
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TypshipInfo {
+    /// The fork of the Universe repository to publish from, as `owner/repo`
+    /// or `repo`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork: Option<String>,
+}
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct TypshipTools {
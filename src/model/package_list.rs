@@ -0,0 +1,129 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// A list of packages to download, as read by `typship download --from`.
+///
+/// ```toml
+/// [[package]]
+/// git = "https://github.com/team/packages"
+/// ref = "v0.3.0"
+/// path = "packages/foo"
+/// namespace = "team"
+///
+/// [[package]]
+/// archive = "bundles/bar-0.1.0.tar.gz"
+///
+/// [[package]]
+/// universe = "@preview/cetz:0.3.1"
+/// namespace = "vendor"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageList {
+    #[serde(default)]
+    pub package: Vec<PackageListEntry>,
+}
+
+/// One entry of a [`PackageList`]. Exactly one of `git`, `archive` and
+/// `universe` must be set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageListEntry {
+    /// Git repository URL
+    pub git: Option<String>,
+    /// Tag, commit, or branch to checkout to
+    #[serde(rename = "ref")]
+    pub checkout: Option<String>,
+    /// Version requirement to pick the tag with, e.g. `^0.3`
+    pub version: Option<String>,
+    /// Directory of the package within the repository
+    pub path: Option<PathBuf>,
+    /// Install every package found in the repository
+    #[serde(default)]
+    pub all: bool,
+    /// The expected name of the package, which also selects it in the
+    /// repository
+    pub name: Option<String>,
+    /// The expected SHA-256 of the package files
+    pub sha256: Option<String>,
+    /// Path to a `.tar.gz` package bundle, relative to the list file
+    pub archive: Option<PathBuf>,
+    /// A published package, e.g. `@preview/cetz:0.3.1`
+    pub universe: Option<String>,
+    /// Namespace to install to, without the `@` prefix
+    pub namespace: Option<String>,
+}
+
+impl PackageList {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let list: Self = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        for (i, entry) in list.package.iter().enumerate() {
+            entry
+                .validate()
+                .with_context(|| format!("Invalid package #{} in {}", i + 1, path.display()))?;
+        }
+        Ok(list)
+    }
+}
+
+impl PackageListEntry {
+    fn validate(&self) -> Result<()> {
+        let sources = [
+            self.git.is_some(),
+            self.archive.is_some(),
+            self.universe.is_some(),
+        ];
+        if sources.iter().filter(|s| **s).count() != 1 {
+            bail!("Expected exactly one of `git`, `archive` and `universe`");
+        }
+        let git_only = self.checkout.is_some()
+            || self.version.is_some()
+            || self.path.is_some()
+            || self.all
+            || self.name.is_some();
+        if self.git.is_none() && git_only {
+            bail!("`ref`, `version`, `path`, `all` and `name` only apply to `git`");
+        }
+        if self.archive.is_some() && self.sha256.is_some() {
+            bail!("`sha256` only applies to `git` and `universe`");
+        }
+        if self.all && (self.name.is_some() || self.sha256.is_some()) {
+            bail!("`name` and `sha256` cannot be used with `all`");
+        }
+        if self.checkout.is_some() && self.version.is_some() {
+            bail!("`ref` and `version` cannot be used together");
+        }
+        if self.path.is_some() && self.all {
+            bail!("`path` and `all` cannot be used together");
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PackageListEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(git) = &self.git {
+            write!(f, "{}", git)?;
+            if let Some(checkout) = &self.checkout {
+                write!(f, "@{}", checkout)?;
+            }
+            if let Some(version) = &self.version {
+                write!(f, " ({})", version)?;
+            }
+            if let Some(path) = &self.path {
+                write!(f, " ({})", path.display())?;
+            }
+        } else if let Some(archive) = &self.archive {
+            write!(f, "{}", archive.display())?;
+        } else if let Some(universe) = &self.universe {
+            write!(f, "{}", universe)?;
+        }
+        Ok(())
+    }
+}
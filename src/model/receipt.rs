@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use typst_syntax::package::{PackageSpec, PackageVersion};
+
+/// Record of a package version that typship placed in the data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Receipt {
+    pub namespace: String,
+    pub name: String,
+    pub version: PackageVersion,
+    pub kind: InstallKind,
+    pub source: Source,
+    /// Seconds since the Unix epoch
+    pub installed_at: u64,
+    /// SHA-256 of every installed file, keyed by its path relative to the
+    /// version directory
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, String>,
+}
+
+impl Receipt {
+    pub fn new(spec: &PackageSpec, kind: InstallKind, source: Source) -> Self {
+        Self {
+            namespace: spec.namespace.to_string(),
+            name: spec.name.to_string(),
+            version: spec.version,
+            kind,
+            source,
+            installed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn spec(&self) -> PackageSpec {
+        PackageSpec {
+            namespace: self.namespace.as_str().into(),
+            name: self.name.as_str().into(),
+            version: self.version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstallKind {
+    /// Files copied by `install`
+    Copy,
+    /// Files hard linked by `install --link hardlink`
+    Hardlink,
+    /// Symlink to the package directory created by `install --link symlink`
+    Symlink,
+    /// Symlink to the package directory created by `dev`
+    Dev,
+}
+
+/// Where an installed package came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Source {
+    Path {
+        path: PathBuf,
+    },
+    Git {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        commit: Option<String>,
+        /// Directory of the package within the repository
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subdir: Option<PathBuf>,
+    },
+    /// A `.tar.gz` package bundle
+    Archive {
+        path: PathBuf,
+    },
+    /// A package bundle downloaded from a registry such as packages.typst.org
+    Registry {
+        url: String,
+    },
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Path { path } | Source::Archive { path } => write!(f, "{}", path.display()),
+            Source::Registry { url } => write!(f, "{}", url),
+            Source::Git {
+                url,
+                commit,
+                subdir,
+            } => {
+                write!(f, "{}", url)?;
+                if let Some(commit) = commit {
+                    write!(f, "@{}", commit)?;
+                }
+                if let Some(subdir) = subdir {
+                    write!(f, " ({})", subdir.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
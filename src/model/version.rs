@@ -0,0 +1,146 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use typst_syntax::package::{PackageVersion, VersionBound};
+
+/// A version requirement such as `^0.3`, `~1.2.0`, `>=0.2, <0.4` or `*`,
+/// following Cargo's conventions: a bare version means `^`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReq {
+    /// All must match; empty matches every version
+    comparators: Vec<Comparator>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    bound: VersionBound,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+impl VersionReq {
+    pub fn matches(&self, version: &PackageVersion) -> bool {
+        self.comparators.iter().all(|c| c.matches(version))
+    }
+
+    /// The highest of `versions` matching the requirement.
+    pub fn best_match<'a>(
+        &self,
+        versions: impl IntoIterator<Item = &'a PackageVersion>,
+    ) -> Option<&'a PackageVersion> {
+        versions.into_iter().filter(|v| self.matches(v)).max()
+    }
+}
+
+impl Comparator {
+    fn matches(&self, v: &PackageVersion) -> bool {
+        let b = &self.bound;
+        match self.op {
+            Op::Exact => v.matches_eq(b),
+            Op::Greater => v.matches_gt(b),
+            Op::GreaterEq => v.matches_ge(b),
+            Op::Less => v.matches_lt(b),
+            Op::LessEq => v.matches_le(b),
+            // `~1.2.3` is `>=1.2.3, <1.3.0`, `~1` is `>=1.0.0, <2.0.0`
+            Op::Tilde => {
+                v.matches_ge(b) && v.major == b.major && b.minor.is_none_or(|m| v.minor == m)
+            }
+            // Everything up to the first non-zero component must match
+            Op::Caret => {
+                v.matches_ge(b)
+                    && v.major == b.major
+                    && (b.major > 0
+                        || match (b.minor, b.patch) {
+                            (None, _) => true,
+                            (Some(m), _) if m > 0 => v.minor == m,
+                            (Some(m), None) => v.minor == m,
+                            (Some(m), Some(p)) => v.minor == m && v.patch == p,
+                        })
+            }
+        }
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "*" {
+            return Ok(Self {
+                comparators: vec![],
+            });
+        }
+        let comparators = s
+            .split(',')
+            .map(|part| part.trim().parse())
+            .collect::<Result<_, _>>()?;
+        Ok(Self { comparators })
+    }
+}
+
+impl FromStr for Comparator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (op, bound) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| s.strip_prefix(prefix).map(|rest| (op, rest)))
+        .unwrap_or((Op::Caret, s));
+        let bound = bound.trim();
+        if bound.is_empty() {
+            bail!("Missing version in requirement `{}`", s);
+        }
+        Ok(Self {
+            op,
+            bound: VersionBound::from_str(bound).map_err(|e| anyhow!(e))?,
+        })
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.comparators.is_empty() {
+            return write!(f, "*");
+        }
+        for (i, c) in self.comparators.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let op = match c.op {
+                Op::Exact => "=",
+                Op::Greater => ">",
+                Op::GreaterEq => ">=",
+                Op::Less => "<",
+                Op::LessEq => "<=",
+                Op::Tilde => "~",
+                Op::Caret => "^",
+            };
+            write!(f, "{}{}", op, c.bound)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a git tag such as `v0.3.1` or `0.3.1` as a package version.
+pub fn version_from_tag(tag: &str) -> Option<PackageVersion> {
+    PackageVersion::from_str(tag.strip_prefix('v').unwrap_or(tag)).ok()
+}
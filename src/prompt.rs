@@ -0,0 +1,474 @@
+//! User prompts, abstracted so that commands can run without a terminal.
+//!
+//! Command functions receive a [`Prompter`]. The CLI uses a
+//! [`TerminalPrompter`]; library users and tests may use a
+//! [`ScriptedPrompter`] with predefined answers, or a [`DenyPrompter`].
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::{anyhow, bail, Context, Result};
+use dialoguer::{Confirm, Editor, Input, MultiSelect, Password};
+use log::info;
+use serde::Deserialize;
+
+pub trait Prompter: Send + Sync {
+    /// Ask a yes/no question.
+    fn confirm(&self, prompt: &str, default: bool) -> Result<bool>;
+
+    /// Ask for permission to carry out an action.
+    fn approve(&self, prompt: &str) -> Result<bool>;
+
+    /// Pick any number of items, returning their indices.
+    fn multi_select(&self, prompt: &str, items: &[&str]) -> Result<Vec<usize>>;
+
+    /// Ask for a secret. `hint` tells the user how to provide the value if it
+    /// cannot be asked for.
+    fn password(&self, prompt: &str, hint: &str) -> Result<String>;
+
+    /// Ask for a line of text.
+    fn text(&self, text: Text) -> Result<String>;
+
+    /// Let the user edit a longer text, returning it unchanged if it cannot be
+    /// edited.
+    fn edit(&self, prompt: &str, text: &str) -> Result<String>;
+
+    /// Like [`Prompter::approve`], but a refusal aborts the command.
+    fn proceed(&self, prompt: &str) -> Result<()> {
+        if !self.approve(prompt)? {
+            bail!("Aborted");
+        }
+        Ok(())
+    }
+}
+
+type Validator<'a> = Box<dyn FnMut(&String) -> Result<()> + 'a>;
+
+/// A line of text, modeled after [`dialoguer::Input`].
+pub struct Text<'a> {
+    prompt: String,
+    default: Option<String>,
+    allow_empty: bool,
+    hint: Option<&'a str>,
+    validator: Option<Validator<'a>>,
+}
+
+impl<'a> Text<'a> {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            default: None,
+            allow_empty: false,
+            hint: None,
+            validator: None,
+        }
+    }
+
+    pub fn default(mut self, default: impl Into<String>) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+
+    pub fn allow_empty(mut self, allow_empty: bool) -> Self {
+        self.allow_empty = allow_empty;
+        self
+    }
+
+    /// How to provide the value when prompting is impossible, e.g. the flag
+    /// to pass.
+    pub fn hint(mut self, hint: &'a str) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    pub fn validate_with(mut self, validator: impl FnMut(&String) -> Result<()> + 'a) -> Self {
+        self.validator = Some(Box::new(validator));
+        self
+    }
+
+    pub fn interact(self, prompter: &dyn Prompter) -> Result<String> {
+        prompter.text(self)
+    }
+
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    pub fn default_value(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn hint_text(&self) -> &str {
+        self.hint.unwrap_or_default()
+    }
+
+    /// Run the validator (if any) against `value`.
+    pub fn validate(&mut self, value: &String) -> Result<()> {
+        match self.validator.as_mut() {
+            Some(validator) => validator(value).map_err(|e| anyhow!("{}: {}", self.prompt, e)),
+            None => Ok(()),
+        }
+    }
+
+    /// The value to use when no one can be asked: the default, or an empty
+    /// string if allowed. The value is validated.
+    pub fn fallback(mut self) -> Result<String> {
+        let value = match self.default.take() {
+            Some(default) => default,
+            None if self.allow_empty => String::new(),
+            None => bail!(
+                "{}: a value is required, but there is no one to ask. {}",
+                self.prompt,
+                self.hint_text()
+            ),
+        };
+        self.validate(&value)?;
+        info!("{}: {}", self.prompt, value);
+        Ok(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Interaction {
+    /// Answer "yes" to every confirmation (`--yes`)
+    pub assume_yes: bool,
+    /// Whether prompts may be shown at all (disabled by `--no-input` or when
+    /// there is no terminal)
+    pub interactive: bool,
+}
+
+impl Interaction {
+    pub fn detect(assume_yes: bool, no_input: bool) -> Self {
+        Self {
+            assume_yes,
+            interactive: !no_input
+                && std::io::stdin().is_terminal()
+                && std::io::stderr().is_terminal(),
+        }
+    }
+}
+
+/// Prompts on the terminal. Without one, defaults are taken and
+/// confirmations fail unless `--yes` is given. With `--yes`, confirmations
+/// are approved and yes/no questions take their default without asking.
+#[derive(Debug, Default)]
+pub struct TerminalPrompter {
+    interaction: Interaction,
+}
+
+impl TerminalPrompter {
+    pub const fn new(interaction: Interaction) -> Self {
+        Self { interaction }
+    }
+}
+
+impl Prompter for TerminalPrompter {
+    fn confirm(&self, prompt: &str, default: bool) -> Result<bool> {
+        let answer = if default { "yes" } else { "no" };
+        if self.interaction.assume_yes {
+            info!("{} {} (--yes)", prompt, answer);
+            return Ok(default);
+        }
+        if !self.interaction.interactive {
+            info!("{} {}", prompt, answer);
+            return Ok(default);
+        }
+        Ok(Confirm::new()
+            .with_prompt(prompt)
+            .default(default)
+            .interact()?)
+    }
+
+    fn approve(&self, prompt: &str) -> Result<bool> {
+        if self.interaction.assume_yes {
+            info!("{} yes (--yes)", prompt);
+            return Ok(true);
+        }
+        if !self.interaction.interactive {
+            bail!(
+                "{}\nCannot ask for confirmation without a terminal. Pass `--yes` to approve.",
+                prompt
+            );
+        }
+        Ok(Confirm::new()
+            .with_prompt(prompt)
+            .default(false)
+            .interact()?)
+    }
+
+    fn multi_select(&self, prompt: &str, items: &[&str]) -> Result<Vec<usize>> {
+        if !self.interaction.interactive {
+            return Ok(vec![]);
+        }
+        Ok(MultiSelect::new()
+            .with_prompt(prompt)
+            .items(items)
+            .interact()?)
+    }
+
+    fn password(&self, prompt: &str, hint: &str) -> Result<String> {
+        if !self.interaction.interactive {
+            bail!("Cannot ask for a secret without a terminal. {}", hint);
+        }
+        Ok(Password::new().with_prompt(prompt).interact()?)
+    }
+
+    fn text(&self, mut text: Text) -> Result<String> {
+        if !self.interaction.interactive {
+            return text.fallback();
+        }
+        let mut input = Input::<String>::new()
+            .with_prompt(text.prompt)
+            .allow_empty(text.allow_empty);
+        if let Some(default) = text.default {
+            input = input.default(default);
+        }
+        if let Some(mut validator) = text.validator.take() {
+            input = input.validate_with(move |s: &String| validator(s));
+        }
+        Ok(input.interact_text()?)
+    }
+
+    fn edit(&self, prompt: &str, text: &str) -> Result<String> {
+        if !self.interaction.interactive {
+            return Ok(text.into());
+        }
+        info!("{}", prompt);
+        // `None` if the editor was closed without saving
+        Ok(Editor::new()
+            .extension(".md")
+            .edit(text)?
+            .unwrap_or_else(|| text.into()))
+    }
+}
+
+/// A predefined answer to a prompt.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Answer {
+    Bool(bool),
+    Text(String),
+    /// Selected items, by their text
+    Items(Vec<String>),
+}
+
+impl From<bool> for Answer {
+    fn from(value: bool) -> Self {
+        Answer::Bool(value)
+    }
+}
+
+impl From<&str> for Answer {
+    fn from(value: &str) -> Self {
+        Answer::Text(value.into())
+    }
+}
+
+impl From<String> for Answer {
+    fn from(value: String) -> Self {
+        Answer::Text(value)
+    }
+}
+
+impl From<Vec<&str>> for Answer {
+    fn from(value: Vec<&str>) -> Self {
+        Answer::Items(value.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Answers prompts from a table keyed by the prompt text.
+///
+/// Unanswered prompts go to a [`TerminalPrompter`], by default one without a
+/// terminal and without `--yes`: defaults are taken and confirmations fail.
+#[derive(Debug, Default)]
+pub struct ScriptedPrompter {
+    answers: HashMap<String, Answer>,
+    fallback: TerminalPrompter,
+}
+
+impl ScriptedPrompter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn answer(mut self, prompt: impl Into<String>, answer: impl Into<Answer>) -> Self {
+        self.answers.insert(prompt.into(), answer.into());
+        self
+    }
+
+    /// Handle unanswered prompts as a [`TerminalPrompter`] with `interaction`
+    /// would.
+    pub fn fallback(mut self, interaction: Interaction) -> Self {
+        self.fallback = TerminalPrompter::new(interaction);
+        self
+    }
+
+    /// Load answers from a TOML file mapping prompts to booleans, strings, or
+    /// lists of selected items.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let answers = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the answer file {}", path.display()))?;
+        let answers = toml::from_str(&answers).context("Failed to parse the answer file")?;
+        Ok(Self {
+            answers,
+            ..Self::default()
+        })
+    }
+
+    fn get(&self, prompt: &str) -> Option<&Answer> {
+        self.answers.get(prompt)
+    }
+
+    fn wrong_type(prompt: &str, expected: &str) -> anyhow::Error {
+        anyhow!("{}\nThe provided answer is not {}", prompt, expected)
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn confirm(&self, prompt: &str, default: bool) -> Result<bool> {
+        match self.get(prompt) {
+            Some(Answer::Bool(b)) => Ok(*b),
+            Some(_) => Err(Self::wrong_type(prompt, "a boolean")),
+            None => self.fallback.confirm(prompt, default),
+        }
+    }
+
+    fn approve(&self, prompt: &str) -> Result<bool> {
+        match self.get(prompt) {
+            Some(Answer::Bool(b)) => Ok(*b),
+            Some(_) => Err(Self::wrong_type(prompt, "a boolean")),
+            None => self.fallback.approve(prompt),
+        }
+    }
+
+    fn multi_select(&self, prompt: &str, items: &[&str]) -> Result<Vec<usize>> {
+        match self.get(prompt) {
+            Some(Answer::Items(selected)) => selected
+                .iter()
+                .map(|s| {
+                    items
+                        .iter()
+                        .position(|i| i == s)
+                        .ok_or_else(|| anyhow!("{}\n`{}` is not a valid choice", prompt, s))
+                })
+                .collect(),
+            Some(_) => Err(Self::wrong_type(prompt, "a list")),
+            None => self.fallback.multi_select(prompt, items),
+        }
+    }
+
+    fn password(&self, prompt: &str, hint: &str) -> Result<String> {
+        match self.get(prompt) {
+            Some(Answer::Text(s)) => Ok(s.clone()),
+            Some(_) => Err(Self::wrong_type(prompt, "a string")),
+            None => self.fallback.password(prompt, hint),
+        }
+    }
+
+    fn text(&self, mut text: Text) -> Result<String> {
+        match self.get(text.prompt()) {
+            Some(Answer::Text(s)) => {
+                if s.is_empty() && !text.allow_empty {
+                    bail!("{}\nAn empty answer is not allowed", text.prompt());
+                }
+                text.validate(s)?;
+                Ok(s.clone())
+            }
+            Some(_) => Err(Self::wrong_type(text.prompt(), "a string")),
+            None => self.fallback.text(text),
+        }
+    }
+
+    fn edit(&self, prompt: &str, text: &str) -> Result<String> {
+        match self.get(prompt) {
+            Some(Answer::Text(s)) => Ok(s.clone()),
+            Some(_) => Err(Self::wrong_type(prompt, "a string")),
+            None => self.fallback.edit(prompt, text),
+        }
+    }
+}
+
+/// Refuses every prompt.
+#[derive(Debug, Default)]
+pub struct DenyPrompter;
+
+impl Prompter for DenyPrompter {
+    fn confirm(&self, _prompt: &str, _default: bool) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn approve(&self, _prompt: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn multi_select(&self, _prompt: &str, _items: &[&str]) -> Result<Vec<usize>> {
+        Ok(vec![])
+    }
+
+    fn password(&self, prompt: &str, _hint: &str) -> Result<String> {
+        bail!("{}\nPrompt denied", prompt)
+    }
+
+    fn text(&self, text: Text) -> Result<String> {
+        bail!("{}\nPrompt denied", text.prompt())
+    }
+
+    fn edit(&self, _prompt: &str, text: &str) -> Result<String> {
+        Ok(text.into())
+    }
+}
+
+/// Lets threads share a [`Prompter`], asking one question at a time.
+pub struct LockedPrompter<'a> {
+    inner: &'a dyn Prompter,
+    lock: Mutex<()>,
+}
+
+impl<'a> LockedPrompter<'a> {
+    pub fn new(inner: &'a dyn Prompter) -> Self {
+        Self {
+            inner,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        // A panic while prompting does not leave anything inconsistent
+        self.lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Prompter for LockedPrompter<'_> {
+    fn confirm(&self, prompt: &str, default: bool) -> Result<bool> {
+        let _guard = self.lock();
+        self.inner.confirm(prompt, default)
+    }
+
+    fn approve(&self, prompt: &str) -> Result<bool> {
+        let _guard = self.lock();
+        self.inner.approve(prompt)
+    }
+
+    fn multi_select(&self, prompt: &str, items: &[&str]) -> Result<Vec<usize>> {
+        let _guard = self.lock();
+        self.inner.multi_select(prompt, items)
+    }
+
+    fn password(&self, prompt: &str, hint: &str) -> Result<String> {
+        let _guard = self.lock();
+        self.inner.password(prompt, hint)
+    }
+
+    fn text(&self, text: Text) -> Result<String> {
+        let _guard = self.lock();
+        self.inner.text(text)
+    }
+
+    fn edit(&self, prompt: &str, text: &str) -> Result<String> {
+        let _guard = self.lock();
+        self.inner.edit(prompt, text)
+    }
+}
@@ -0,0 +1,120 @@
+//! Where registry tokens come from, so that they need not be stored in the
+//! config file.
+
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+use secrecy::SecretString;
+
+use crate::config::CONFIG;
+
+/// Environment variables holding the Universe token, in order of precedence.
+pub const UNIVERSE_TOKEN_VARS: [&str; 2] = ["TYPSHIP_UNIVERSE_TOKEN", "GITHUB_TOKEN"];
+
+#[derive(Debug, Clone)]
+pub enum TokenSource {
+    /// `--token-file`
+    File(PathBuf),
+    /// An environment variable
+    Env(&'static str),
+    /// The `credential_helper` command of the config
+    Helper(String),
+    /// `tokens.universe` of the config, as saved by `typship login`
+    Config,
+}
+
+impl fmt::Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenSource::File(path) => write!(f, "the token file {}", path.display()),
+            TokenSource::Env(var) => write!(f, "${}", var),
+            TokenSource::Helper(helper) => write!(f, "the credential helper `{}`", helper),
+            TokenSource::Config => write!(f, "the config file"),
+        }
+    }
+}
+
+/// Find the Universe token for `host`. The first of these wins: the
+/// `token_file`, the [`UNIVERSE_TOKEN_VARS`], the credential helper of the
+/// config, and the token saved in the config.
+pub fn universe_token(
+    token_file: Option<&Path>,
+    host: &str,
+) -> Result<Option<(SecretString, TokenSource)>> {
+    universe_token_in(token_file, host, |var| std::env::var(var).ok())
+}
+
+/// [`universe_token`] with the environment variables looked up by `env`.
+pub fn universe_token_in(
+    token_file: Option<&Path>,
+    host: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Option<(SecretString, TokenSource)>> {
+    if let Some(path) = token_file {
+        let token = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the token file {}", path.display()))?;
+        let token = token.trim();
+        if token.is_empty() {
+            bail!("The token file {} is empty", path.display());
+        }
+        return Ok(Some((token.into(), TokenSource::File(path.into()))));
+    }
+    for var in UNIVERSE_TOKEN_VARS {
+        if let Some(token) = env(var).filter(|t| !t.trim().is_empty()) {
+            return Ok(Some((token.trim().into(), TokenSource::Env(var))));
+        }
+    }
+    let (helper, saved) = {
+        let config = CONFIG.try_lock()?;
+        (
+            config.tokens.credential_helper.clone(),
+            config.tokens.universe.clone(),
+        )
+    };
+    if let Some(helper) = helper {
+        if let Some(token) = token_from_helper(&helper, host)? {
+            return Ok(Some((token.into(), TokenSource::Helper(helper))));
+        }
+    }
+    Ok(saved.map(|token| (token.into(), TokenSource::Config)))
+}
+
+/// Ask `helper` for the password of `host` with the git credential protocol,
+/// i.e. run `<helper> get` with the request on stdin.
+pub fn token_from_helper(helper: &str, host: &str) -> Result<Option<String>> {
+    debug!("Asking the credential helper `{}`", helper);
+    let line = format!("{} get", helper);
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", &line]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", &line]);
+        command
+    };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run the credential helper `{}`", helper))?;
+    let request = format!("protocol=https\nhost={}\n\n", host);
+    match child.stdin.take().unwrap().write_all(request.as_bytes()) {
+        // Helpers may answer without reading the request
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        res => res?,
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("The credential helper `{}` failed", helper);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .map(|password| password.trim().to_string())
+        .filter(|password| !password.is_empty()))
+}
@@ -1 +1,2 @@
+pub mod credentials;
 pub mod universe;
@@ -1,108 +1,252 @@
 /// Typst Official Package Registry: Universeuse anyhow::anyhow;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
 use clap::ValueEnum;
 use crossterm::style::Stylize;
-use dialoguer::{Confirm, Input};
 use futures_util::TryStreamExt;
 use log::{info, warn};
 use octocrab::models::pulls::PullRequest;
-use octocrab::models::repos::{ContentItems, Object};
-use octocrab::{params, Octocrab, Page};
-use regex::Regex;
-use secrecy::SecretString;
-use std::process::Command;
-use tempfile::TempDir;
+use octocrab::models::repos::Object;
+use octocrab::{params, Octocrab};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
 use typst_syntax::package::{PackageManifest, PackageVersion};
+use url::Url;
 
-use crate::config::CONFIG;
+use crate::commands::check::license_files;
+use crate::config::{UniverseConfig, CONFIG};
+use crate::model::manifest::{GetTools, TypshipTools};
+use crate::prompt::{Prompter, Text};
+use crate::regs::credentials::{universe_token, UNIVERSE_TOKEN_VARS};
+use crate::utils::git::{self, CloneOptions};
+use crate::utils::temp::WorkDir;
 use crate::utils::walkers::walker_publish;
-use crate::utils::{config_file, save_config};
+use crate::utils::{cache_dir, config_file, git_blob_sha1, save_config};
 
 // pub const UNIVERSE_REPO_ID: RepositoryId =
 // RepositoryId::from("R_kgDOJ0PIWA");
 pub const UNIVERSE_REPO_NAME: &str = "packages";
 pub const UNIVERSE_REPO_OWNER: &str = "typst";
 
-/// Unauthorized client for public access
-pub static PUBLIC_CLIENT: LazyLock<Octocrab> =
-    LazyLock::new(|| Octocrab::builder().build().unwrap());
+pub const UNIVERSE_BRANCH: &str = "main";
+pub const GITHUB_URL: &str = "https://github.com";
 
-pub static AUTH_CLIENT: OnceLock<Octocrab> = OnceLock::new();
+/// How long to wait for GitHub to create a fork
+const FORK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const FORK_POLL_INTERVAL: Duration = Duration::from_secs(3);
 
-pub fn get_authenticated_client() -> Result<&'static Octocrab> {
-    // TODO: better secret management
-    // TODO: Lock retry?
-    let token = CONFIG
-        .try_lock()?
-        .tokens
-        .universe
-        .clone()
-        .ok_or(anyhow::anyhow!(
-            "You need to set up the token first. Run `typship login universe`."
-        ))?;
-    let token = SecretString::from(token);
-    Ok(AUTH_CLIENT.get_or_init(|| Octocrab::builder().personal_token(token).build().unwrap()))
+/// The repository packages are submitted to: typst/packages on GitHub, unless
+/// configured otherwise in `[universe]` of the config.
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    pub owner: String,
+    pub repo: String,
+    /// The branch submissions are based on and merged into
+    pub branch: String,
+    /// Base URL of the GitHub API, `None` for api.github.com
+    pub api_url: Option<String>,
+    /// Base URL of the web and git server
+    pub web_url: String,
+}
+
+impl Default for Upstream {
+    fn default() -> Self {
+        Self::from_config(&UniverseConfig::default())
+    }
 }
 
-/// Get the list of package names under `packages/preview` directory in the
+impl Upstream {
+    pub fn from_config(config: &UniverseConfig) -> Self {
+        let api_url = config
+            .api_url
+            .as_ref()
+            .map(|url| url.trim_end_matches('/').to_string());
+        // GitHub Enterprise serves the API at `<web_url>/api/v3`
+        let web_url = match (&config.web_url, &api_url) {
+            (Some(url), _) => url.trim_end_matches('/').to_string(),
+            (None, Some(url)) => url.trim_end_matches("/api/v3").to_string(),
+            (None, None) => GITHUB_URL.to_string(),
+        };
+        Self {
+            owner: config
+                .owner
+                .as_deref()
+                .unwrap_or(UNIVERSE_REPO_OWNER)
+                .into(),
+            repo: config.repo.as_deref().unwrap_or(UNIVERSE_REPO_NAME).into(),
+            branch: config.branch.as_deref().unwrap_or(UNIVERSE_BRANCH).into(),
+            api_url,
+            web_url,
+        }
+    }
+
+    /// The host of the web server, for credential helpers.
+    pub fn host(&self) -> String {
+        Url::parse(&self.web_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "github.com".into())
+    }
+
+    /// The URL to clone `owner/repo` from.
+    pub fn git_url(&self, owner: &str, repo: &str) -> String {
+        format!("{}/{}/{}.git", self.web_url, owner, repo)
+    }
+
+    /// An API client, unauthorized if no `token` is given.
+    pub fn client(&self, token: Option<&SecretString>) -> Result<Octocrab> {
+        let mut builder = Octocrab::builder();
+        if let Some(url) = &self.api_url {
+            builder = builder.base_uri(url.as_str())?;
+        }
+        if let Some(token) = token {
+            builder = builder.personal_token(token.clone());
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// Find the token as described in [`universe_token`].
+pub fn get_token(upstream: &Upstream, token_file: Option<&Path>) -> Result<SecretString> {
+    let (token, source) = universe_token(token_file, &upstream.host())?.ok_or(anyhow!(
+        "You need to set up the token first. Run `typship login universe`, set ${}, or pass `--token-file`.",
+        UNIVERSE_TOKEN_VARS[0]
+    ))?;
+    info!("Using the token from {}", source);
+    Ok(token)
+}
+
+pub fn get_authenticated_client(upstream: &Upstream, token: &SecretString) -> Result<Octocrab> {
+    upstream.client(Some(token))
+}
+
+/// A directory listing of the git trees API.
+#[derive(Debug, Deserialize)]
+struct GitTree {
+    tree: Vec<GitTreeEntry>,
+    truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitTreeEntry {
+    /// The name of the entry, as the tree is not fetched recursively
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+    sha: String,
+}
+
+/// The names of the subdirectories of `path` on the upstream branch.
+///
+/// The git trees API is used since the contents API lists at most 1000
+/// entries, fewer than Universe has packages. With a `token`, the rate limit
+/// is that of the user instead of the much lower anonymous one.
+async fn subdirectories(
+    upstream: &Upstream,
+    token: Option<&SecretString>,
+    path: &str,
+) -> Result<Vec<String>> {
+    let client = upstream.client(token)?;
+    let get_tree = |tree: String| {
+        let route = format!(
+            "/repos/{}/{}/git/trees/{}",
+            upstream.owner, upstream.repo, tree
+        );
+        let client = &client;
+        async move { client.get::<GitTree, _, ()>(route, None).await }
+    };
+    let mut tree = get_tree(upstream.branch.clone()).await?;
+    for name in path.split('/') {
+        let sha = tree
+            .tree
+            .into_iter()
+            .find(|entry| entry.path == name && entry.kind == "tree")
+            .ok_or(anyhow!(
+                "`{}` does not exist in {}/{}",
+                path,
+                upstream.owner,
+                upstream.repo
+            ))?
+            .sha;
+        tree = get_tree(sha).await?;
+    }
+    if tree.truncated {
+        bail!("The listing of `{}` is incomplete", path);
+    }
+    Ok(tree
+        .tree
+        .into_iter()
+        .filter(|entry| entry.kind == "tree")
+        .map(|entry| entry.path)
+        .collect())
+}
+
+/// Get the names of all packages under `packages/preview` directory in the
 /// official Universe (GitHub) registry.
-pub async fn packages() -> Result<ContentItems> {
-    Ok(PUBLIC_CLIENT
-        .repos(UNIVERSE_REPO_OWNER, UNIVERSE_REPO_NAME)
-        .get_content()
-        .path("packages/preview")
-        .r#ref("main")
-        .send()
-        .await?)
+pub async fn packages(upstream: &Upstream, token: Option<&SecretString>) -> Result<Vec<String>> {
+    subdirectories(upstream, token, "packages/preview").await
 }
 
-/// Get the list of package versions under `packages/preview/{package_name}`
-/// directory in the official Universe (GitHub) registry.
-pub async fn package_versions(package_name: &str) -> Result<ContentItems> {
-    Ok(PUBLIC_CLIENT
-        .repos(UNIVERSE_REPO_OWNER, UNIVERSE_REPO_NAME)
-        .get_content()
-        .path(format!("packages/preview/{package_name}"))
-        .r#ref("main")
-        .send()
-        .await?)
+/// Get the versions under `packages/preview/{package_name}` directory in the
+/// official Universe (GitHub) registry.
+pub async fn package_versions(
+    upstream: &Upstream,
+    token: Option<&SecretString>,
+    package_name: &str,
+) -> Result<Vec<String>> {
+    subdirectories(upstream, token, &format!("packages/preview/{package_name}")).await
 }
 
-/// Get the list of *OPEN* pull requests in the official Universe (GitHub)
-/// registry.
-pub async fn pending_list() -> Result<Page<PullRequest>> {
-    Ok(PUBLIC_CLIENT
-        .pulls(UNIVERSE_REPO_OWNER, UNIVERSE_REPO_NAME)
+/// Get all *OPEN* pull requests in the official Universe (GitHub) registry.
+pub async fn pending_list(
+    upstream: &Upstream,
+    token: Option<&SecretString>,
+) -> Result<Vec<PullRequest>> {
+    let client = upstream.client(token)?;
+    let first_page = client
+        .pulls(&upstream.owner, &upstream.repo)
         .list()
         .state(octocrab::params::State::Open)
+        .per_page(100)
         .send()
-        .await?)
+        .await?;
+    Ok(client.all_pages(first_page).await?)
 }
 
-pub fn login() -> Result<()> {
+pub fn login(prompter: &dyn Prompter) -> Result<()> {
     let overwrite = if CONFIG.try_lock()?.tokens.universe.is_some() {
         info!("Already logged in to the Universe registry");
-        dialoguer::Confirm::new()
-            .with_prompt("Do you want to overwrite the existing token?")
-            .default(false)
-            .interact()?
+        prompter.approve("Do you want to overwrite the existing token?")?
     } else {
         true
     };
     if !overwrite {
         return Ok(());
     }
-    let token = dialoguer::Password::new()
-        .with_prompt(r#"
-Please create and get a `fine-grained token` from https://github.com/settings/personal-access-tokens/new.
-You must grant the "Contents", "Workflows", and "Pull requests" permission of the typst/packages forks to the token.
+    if let Some(var) = UNIVERSE_TOKEN_VARS
+        .iter()
+        .find(|var| std::env::var(var).is_ok_and(|t| !t.trim().is_empty()))
+    {
+        warn!("${} is set, and takes precedence over the saved token", var);
+    }
+    let upstream = Upstream::from_config(&CONFIG.try_lock()?.universe);
+    let token = prompter.password(
+        format!(
+            r#"
+Please create and get a `fine-grained token` from {}/settings/personal-access-tokens/new.
+You must grant the "Contents", "Workflows", and "Pull requests" permission of the {}/{} forks to the token.
 Enter your GitHub personal access token
-"#.trim())
-        .interact()?;
+"#,
+            upstream.web_url, upstream.owner, upstream.repo
+        )
+        .trim(),
+        "Run `typship login universe` in a terminal.",
+    )?;
     CONFIG.try_lock()?.tokens.universe = Some(token);
     if let Ok(cfg) = CONFIG.try_lock() {
         save_config(&cfg)?;
@@ -117,30 +261,65 @@ Enter your GitHub personal access token
 pub enum UploadMethod {
     Sparse,
     Api,
+    /// A single commit through the Git Data API
+    GitData,
+}
+
+/// How [`publish`] submits a package.
+#[derive(Debug, Clone)]
+pub struct PublishOptions {
+    /// Make no changes to the fork or the registry
+    pub dry_run: bool,
+    pub upload_method: UploadMethod,
+    /// Keep the sparse checkout in the temp directory, for debugging
+    pub keep: bool,
+    /// The fork to submit from, as `owner/repo` or `repo` of the
+    /// authenticated user
+    pub fork: Option<String>,
+    /// File to read the token from, instead of the environment or config
+    pub token_file: Option<PathBuf>,
+    /// File with the PR description, instead of the generated one
+    pub message_file: Option<PathBuf>,
+    pub upstream: Upstream,
+}
+
+/// The fork given by `--fork` (`option`), `[tool.typship]` of the manifest,
+/// or the config, in this order.
+pub fn fork_setting(
+    option: Option<&str>,
+    manifest: &PackageManifest,
+    config: Option<&str>,
+) -> Option<String> {
+    if let Some(fork) = option {
+        return Some(fork.to_string());
+    }
+    let tools: TypshipTools = manifest.tools();
+    if let Some(fork) = tools.typship.and_then(|t| t.fork) {
+        return Some(fork);
+    }
+    config.map(str::to_string)
 }
 
 pub async fn publish(
     manifest: &PackageManifest,
     package_dir: &Path,
-    dry_run: bool,
-    upload_method: UploadMethod,
+    options: &PublishOptions,
+    prompter: &dyn Prompter,
 ) -> Result<()> {
+    let dry_run = options.dry_run;
+    let upstream = &options.upstream;
+    let token = get_token(upstream, options.token_file.as_deref())?;
     // TODO: check if exist in package repo(name), check pr
     info!("Checking the packages in the official packages repo...");
     let mut is_new_package = true;
-    let packages = packages().await?;
-    for package in packages.items {
-        if package.name != manifest.package.name {
+    let packages = packages(upstream, Some(&token)).await?;
+    for package in packages {
+        if package != manifest.package.name {
             continue;
         }
-        info!("Package `{}` found in official packages repo", package.name);
+        info!("Package `{}` found in official packages repo", package);
         is_new_package = false;
-        let versions = package_versions(&package.name).await?;
-        let existing_versions = versions
-            .items
-            .into_iter()
-            .map(|v| v.name)
-            .collect::<Vec<_>>();
+        let existing_versions = package_versions(upstream, Some(&token), &package).await?;
         info!("Existing versions: {}", existing_versions.join(", "));
         if existing_versions.contains(&manifest.package.version.to_string()) {
             bail!(
@@ -151,11 +330,14 @@ pub async fn publish(
     }
 
     info!("Checking the pending PRs...");
-    let prs = pending_list().await?;
-    for pr in prs.items {
+    let prs = pending_list(upstream, Some(&token)).await?;
+    // The PR for this version, which is updated if it comes from the fork
+    let mut submitted = None;
+    for pr in prs {
         if let Some(submission) = pr
             .title
-            .and_then(|t| PackageSubmission::try_from_title(&t).ok())
+            .as_deref()
+            .and_then(|t| PackageSubmission::try_from_title(t).ok())
         {
             if submission.name == manifest.package.name {
                 match submission.version.cmp(&manifest.package.version) {
@@ -168,12 +350,7 @@ pub async fn publish(
                         );
                     }
                     std::cmp::Ordering::Equal => {
-                        bail!(
-                            "Package version `{}`(current) is already submitted in PR #{}: {}",
-                            submission.version,
-                            pr.number,
-                            pr.url.underlined()
-                        );
+                        submitted = Some(pr);
                     }
                     std::cmp::Ordering::Less => {
                         warn!(
@@ -188,58 +365,106 @@ pub async fn publish(
         }
     }
 
-    // TODO: confirm the files, pr message, etc.
     let submission = PackageSubmission {
         name: manifest.package.name.clone().into(),
         version: manifest.package.version,
-        msg: Some(SubmissionMessage {
-            is_new_package,
-            desc: manifest
-                .package
-                .description
-                .clone()
-                .ok_or(anyhow!("Missing description"))?
-                .into(),
-        }),
     };
 
     // Danger zone
-    let client = get_authenticated_client()?;
+    let client = &get_authenticated_client(upstream, &token)?;
     let me = client.current().user().await?;
-    let my_repo: String = Input::new()
-        .with_prompt("Enter the name of your forked repository")
-        .allow_empty(false)
-        .default(UNIVERSE_REPO_NAME.into())
-        .interact_text()?;
-    let my_fork = client.repos(&me.login, &my_repo);
-    let parent = my_fork.get().await?.parent;
-    if let Some(p) = parent {
-        if p.name != UNIVERSE_REPO_NAME
-            || p.owner
-                .map(|o| o.login != UNIVERSE_REPO_OWNER)
-                .unwrap_or(true)
-        {
-            bail!("The given repository is not a fork of the official packages repo");
+    let config_fork = CONFIG.try_lock()?.universe.fork.clone();
+    let fork = match fork_setting(options.fork.as_deref(), manifest, config_fork.as_deref()) {
+        Some(fork) => fork,
+        None => Text::new("Enter your fork of the packages repository (`repo` or `owner/repo`)")
+            .allow_empty(false)
+            .default(upstream.repo.as_str())
+            .interact(prompter)?,
+    };
+    let (fork_owner, my_repo) = match fork.split_once('/') {
+        Some((owner, repo)) => (owner.to_string(), repo.to_string()),
+        None => (me.login.clone(), fork),
+    };
+    info!("Using the fork {}/{}", fork_owner, my_repo);
+    let my_fork = client.repos(&fork_owner, &my_repo);
+    // New branches are based on the upstream, however old the fork is
+    let main_sha = branch_head(client, &upstream.owner, &upstream.repo, &upstream.branch).await?;
+    match my_fork.get().await {
+        Ok(repo) => {
+            if let Some(p) = repo.parent {
+                if p.name != upstream.repo
+                    || p.owner.map(|o| o.login != upstream.owner).unwrap_or(true)
+                {
+                    bail!("The given repository is not a fork of the official packages repo");
+                }
+            } else {
+                bail!("The given repository is not a fork");
+            }
+            if !dry_run {
+                sync_fork(
+                    client,
+                    (&fork_owner, &my_repo),
+                    upstream,
+                    &main_sha,
+                    prompter,
+                )
+                .await?;
+            }
         }
-    } else {
-        bail!("The given repository is not a fork");
+        Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404 => {
+            if dry_run {
+                info!(
+                    "Dry run: `{}/{}` does not exist, fork creation skipped",
+                    fork_owner, my_repo
+                );
+            } else {
+                prompter.proceed(&format!(
+                    "`{}/{}` does not exist. Do you want to fork {}/{} to it?",
+                    fork_owner, my_repo, upstream.owner, upstream.repo
+                ))?;
+                create_fork(client, upstream, &me.login, (&fork_owner, &my_repo)).await?;
+            }
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let head = format!("{}:{}", fork_owner, submission.branch_name());
+    let files = publish_files(package_dir);
+    match submitted {
+        Some(pr) if pr.head.label.as_ref() == Some(&head) => {
+            let fork = (fork_owner.as_str(), my_repo.as_str());
+            return update_submission(
+                client,
+                fork,
+                &submission,
+                &pr,
+                package_dir,
+                &files,
+                &token,
+                options,
+                prompter,
+            )
+            .await;
+        }
+        Some(pr) => bail!(
+            "Package version `{}`(current) is already submitted in PR #{}: {}",
+            submission.version,
+            pr.number,
+            pr.url.underlined()
+        ),
+        None => {}
+    }
+
+    let body = submission_body(manifest, package_dir, is_new_package, options, prompter).await?;
+    if body.trim().is_empty() {
+        bail!("Empty PR description, aborted");
     }
+    info!("PR description:\n{}", body);
 
     info!("Creating corresponding branch in your fork...");
     if !dry_run {
         let branch_name = submission.branch_name().clone();
         let branch_name = branch_name.as_str();
-        let main_head = client
-            .repos(UNIVERSE_REPO_OWNER, UNIVERSE_REPO_NAME)
-            .get_ref(&params::repos::Reference::Branch("main".into()))
-            .await?
-            .object;
-        let main_sha = match &main_head {
-            Object::Commit { sha, .. } => sha,
-            Object::Tag { sha, .. } => sha,
-            _ => unreachable!(),
-        };
-
         if my_fork
             .list_branches()
             .send()
@@ -248,16 +473,10 @@ pub async fn publish(
             .try_any(|b| async move { b.name == branch_name })
             .await?
         {
-            if !Confirm::new()
-                .with_prompt(format!(
-                    "Branch `{}` already exists in your fork. Do you want to overwrite it?",
-                    branch_name
-                ))
-                .default(false)
-                .interact()?
-            {
-                bail!("Aborted");
-            }
+            prompter.proceed(&format!(
+                "Branch `{}` already exists in your fork. Do you want to overwrite it?",
+                branch_name
+            ))?;
             my_fork
                 .delete_ref(&params::repos::Reference::Branch(branch_name.into()))
                 .await?;
@@ -265,7 +484,7 @@ pub async fn publish(
         let new_branch = my_fork
             .create_ref(
                 &params::repos::Reference::Branch(submission.branch_name()),
-                main_sha,
+                &main_sha,
             )
             .await?;
 
@@ -279,16 +498,6 @@ pub async fn publish(
     }
 
     info!("Uploading files to personal fork...");
-    let mut files = Vec::new();
-
-    for entry in walker_publish(package_dir).flatten() {
-        if !entry.path().is_file() {
-            continue;
-        }
-        let entry = entry.path();
-        let entry = entry.strip_prefix(package_dir).unwrap();
-        files.push(entry.to_path_buf());
-    }
     info!(
         "Files to upload:\n{}",
         files
@@ -298,87 +507,331 @@ pub async fn publish(
             .join("\n")
     );
     if !dry_run {
-        if !Confirm::new()
-            .with_prompt("Do you want to continue?")
-            .default(false)
-            .interact()?
-        {
-            bail!("Aborted");
-        }
+        prompter.proceed("Do you want to continue?")?;
 
-        match upload_method {
-            UploadMethod::Sparse => {
-                let use_sparse_checkout = check_git_version().unwrap_or(false);
-                if use_sparse_checkout {
-                    upload_files_sparse_checkout(
-                        client,
-                        &me.login,
-                        &my_repo,
-                        &submission,
-                        package_dir,
-                        &files,
-                    )
-                    .await?;
-                } else {
-                    info!("Git version does not support sparse-checkout, automatically switch to api upload method.");
-                    upload_files_api(
-                        client,
-                        &me.login,
-                        &my_repo,
-                        &submission,
-                        package_dir,
-                        &files,
-                    )
-                    .await?;
-                }
-            }
-            UploadMethod::Api => {
-                upload_files_api(
-                    client,
-                    &me.login,
-                    &my_repo,
-                    &submission,
-                    package_dir,
-                    &files,
-                )
-                .await?;
-            }
-        }
+        upload_files(
+            client,
+            (&fork_owner, &my_repo),
+            &submission,
+            package_dir,
+            &files,
+            &BTreeMap::new(),
+            &token,
+            options,
+        )
+        .await?;
     } else {
         info!("Dry run: file upload skipped");
     }
 
     info!("Generating submission PR...");
     if !dry_run {
-        if let Some(msg) = &submission.msg {
-            let sub = client
-                .pulls(UNIVERSE_REPO_OWNER, UNIVERSE_REPO_NAME)
-                .create(
-                    submission.title(),
-                    format!("{}:{}", me.login, submission.branch_name()),
-                    "main",
-                )
-                .body(msg.to_string(manifest.template.is_some()))
-                .draft(true)
-                .send()
-                .await?;
-            info!(
-                "PR created: {}",
-                sub.html_url.unwrap().as_str().underlined()
-            );
-        } else {
-            bail!("Missing submission message");
-        }
+        let sub = client
+            .pulls(&upstream.owner, &upstream.repo)
+            .create(
+                submission.title(),
+                format!("{}:{}", fork_owner, submission.branch_name()),
+                &upstream.branch,
+            )
+            .body(body)
+            .draft(true)
+            .send()
+            .await?;
+        info!(
+            "PR created: {}",
+            sub.html_url.unwrap().as_str().underlined()
+        );
     } else {
         info!("Dry run: PR creation skipped");
     }
     Ok(())
 }
 
-struct PackageSubmission {
-    name: String,
-    version: PackageVersion,
-    msg: Option<SubmissionMessage>,
+/// The commit `branch` of a repository points to.
+async fn branch_head(client: &Octocrab, owner: &str, repo: &str, branch: &str) -> Result<String> {
+    let head = client
+        .repos(owner, repo)
+        .get_ref(&params::repos::Reference::Branch(branch.into()))
+        .await?
+        .object;
+    match head {
+        Object::Commit { sha, .. } | Object::Tag { sha, .. } => Ok(sha),
+        _ => bail!("Unexpected object at `{}` of {}/{}", branch, owner, repo),
+    }
+}
+
+/// Fork the upstream repository to `owner/repo` (an organization unless
+/// `owner` is `login`), and wait until it is ready.
+pub async fn create_fork(
+    client: &Octocrab,
+    upstream: &Upstream,
+    login: &str,
+    (owner, repo): (&str, &str),
+) -> Result<()> {
+    info!("Creating the fork {}/{}...", owner, repo);
+    let upstream_repo = client.repos(&upstream.owner, &upstream.repo);
+    let mut request = upstream_repo
+        .create_fork()
+        .name(repo)
+        .default_branch_only(true);
+    if !owner.eq_ignore_ascii_case(login) {
+        request = request.organization(owner);
+    }
+    let fork = request.send().await?;
+    // An existing fork is returned instead of creating another one
+    if !fork.name.eq_ignore_ascii_case(repo) {
+        bail!(
+            "{}/{} is already forked to `{}`. Pass it with `--fork`.",
+            upstream.owner,
+            upstream.repo,
+            fork.full_name.unwrap_or(fork.name)
+        );
+    }
+
+    // GitHub creates forks in the background
+    let start = Instant::now();
+    while start.elapsed() < FORK_TIMEOUT {
+        if branch_head(client, owner, repo, &upstream.branch)
+            .await
+            .is_ok()
+        {
+            info!("Fork {}/{} created", owner, repo);
+            return Ok(());
+        }
+        tokio::time::sleep(FORK_POLL_INTERVAL).await;
+    }
+    bail!(
+        "The fork {}/{} is not ready after {} seconds. Try again later.",
+        owner,
+        repo,
+        FORK_TIMEOUT.as_secs()
+    )
+}
+
+/// Offer to fast-forward the base branch of the fork to `upstream_sha`.
+pub async fn sync_fork(
+    client: &Octocrab,
+    (owner, repo): (&str, &str),
+    upstream: &Upstream,
+    upstream_sha: &str,
+    prompter: &dyn Prompter,
+) -> Result<()> {
+    let branch = &upstream.branch;
+    // The fork may have renamed or removed the branch, which is fine
+    let Ok(fork_sha) = branch_head(client, owner, repo, branch).await else {
+        return Ok(());
+    };
+    if fork_sha == upstream_sha {
+        return Ok(());
+    }
+    if !prompter.confirm(
+        &format!(
+            "`{}` of your fork is not up to date with {}/{}. Do you want to fast-forward it?",
+            branch, upstream.owner, upstream.repo
+        ),
+        false,
+    )? {
+        return Ok(());
+    }
+    let route = format!("/repos/{}/{}/git/refs/heads/{}", owner, repo, branch);
+    let update = serde_json::json!({ "sha": upstream_sha, "force": false });
+    match client
+        .patch::<serde_json::Value, _, _>(route, Some(&update))
+        .await
+    {
+        Ok(_) => info!("Fast-forwarded `{}` of your fork", branch),
+        Err(e) => warn!(
+            "Failed to fast-forward `{}` of your fork, it might have diverged: {}",
+            branch, e
+        ),
+    }
+    Ok(())
+}
+
+/// The files of the package to submit, relative to `package_dir`.
+fn publish_files(package_dir: &Path) -> Vec<PathBuf> {
+    walker_publish(package_dir)
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| {
+            entry
+                .path()
+                .strip_prefix(package_dir)
+                .unwrap()
+                .to_path_buf()
+        })
+        .collect()
+}
+
+/// Push the changes of the package to the branch of its open `pr`, and
+/// optionally comment on the PR with what changed.
+#[allow(clippy::too_many_arguments)]
+async fn update_submission(
+    client: &Octocrab,
+    (fork_owner, fork_repo): (&str, &str),
+    submission: &PackageSubmission,
+    pr: &PullRequest,
+    package_dir: &Path,
+    files: &[PathBuf],
+    token: &SecretString,
+    options: &PublishOptions,
+    prompter: &dyn Prompter,
+) -> Result<()> {
+    let url = pr.html_url.as_ref().map_or(pr.url.clone(), Url::to_string);
+    info!(
+        "Package version `{}` is already submitted from your fork in PR #{}: {}",
+        submission.version,
+        pr.number,
+        url.underlined()
+    );
+    let remote = branch_files(
+        client,
+        fork_owner,
+        fork_repo,
+        &submission.branch_name(),
+        &submission.repo_path(),
+    )
+    .await?;
+    let changes = FileChanges::new(&remote, package_dir, files)?;
+    if changes.is_empty() {
+        info!("PR #{} is up to date", pr.number);
+        return Ok(());
+    }
+    info!("Changes to PR #{}:\n{}", pr.number, changes);
+    if options.dry_run {
+        info!("Dry run: update of PR #{} skipped", pr.number);
+        return Ok(());
+    }
+    prompter.proceed(&format!(
+        "Do you want to push these changes to PR #{}?",
+        pr.number
+    ))?;
+    upload_files(
+        client,
+        (fork_owner, fork_repo),
+        submission,
+        package_dir,
+        files,
+        &remote,
+        token,
+        options,
+    )
+    .await?;
+    info!("PR #{} updated", pr.number);
+
+    if prompter.confirm(
+        &format!(
+            "Do you want to comment on PR #{} with the changes?",
+            pr.number
+        ),
+        false,
+    )? {
+        let upstream = &options.upstream;
+        client
+            .issues(&upstream.owner, &upstream.repo)
+            .create_comment(pr.number, format!("Updated the package:\n\n{}", changes))
+            .await?;
+    }
+    Ok(())
+}
+
+/// The files under `dir` on `branch` of a repository, keyed by their path
+/// relative to `dir`, with their blob SHA.
+async fn branch_files(
+    client: &Octocrab,
+    owner: &str,
+    repo: &str,
+    branch: &str,
+    dir: &Path,
+) -> Result<BTreeMap<PathBuf, String>> {
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        let items = client
+            .repos(owner, repo)
+            .get_content()
+            .path(current.to_string_lossy())
+            .r#ref(branch)
+            .send()
+            .await?;
+        for item in items.items {
+            let path = PathBuf::from(item.path);
+            if item.r#type == "dir" {
+                dirs.push(path);
+            } else {
+                files.insert(path.strip_prefix(dir)?.to_path_buf(), item.sha);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// How the package files differ from those in the submission branch.
+#[derive(Debug, Default)]
+pub struct FileChanges {
+    pub added: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl FileChanges {
+    /// Compare `files` in `package_dir` with the `remote` ones, given with
+    /// their git blob SHA.
+    pub fn new(
+        remote: &BTreeMap<PathBuf, String>,
+        package_dir: &Path,
+        files: &[PathBuf],
+    ) -> Result<Self> {
+        let mut changes = Self::default();
+        for file in files {
+            match remote.get(file) {
+                None => changes.added.push(file.clone()),
+                Some(sha) => {
+                    if *sha != git_blob_sha1(&std::fs::read(package_dir.join(file))?) {
+                        changes.modified.push(file.clone());
+                    }
+                }
+            }
+        }
+        changes.removed = remote
+            .keys()
+            .filter(|file| !files.contains(file))
+            .cloned()
+            .collect();
+        Ok(changes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl std::fmt::Display for FileChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let groups = [
+            ("Added", &self.added),
+            ("Modified", &self.modified),
+            ("Removed", &self.removed),
+        ];
+        let mut lines = groups.iter().flat_map(|(kind, files)| {
+            files
+                .iter()
+                .map(move |file| format!("- {} `{}`", kind, file.display()))
+        });
+        if let Some(first) = lines.next() {
+            write!(f, "{}", first)?;
+        }
+        for line in lines {
+            write!(f, "\n{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// A package version submitted to the packages repository.
+pub struct PackageSubmission {
+    pub name: String,
+    pub version: PackageVersion,
 }
 
 impl PackageSubmission {
@@ -401,75 +854,411 @@ impl PackageSubmission {
         }
         let name = parts[0].to_string();
         let version = PackageVersion::from_str(parts[1]).map_err(|_| anyhow!("Invalid version"))?;
-        Ok(Self {
-            name,
-            version,
-            msg: None,
-        })
+        Ok(Self { name, version })
     }
 }
 
-struct SubmissionMessage {
-    /// I am submitting
-    /// - [ ] a new package
-    /// - [ ] an update for a package
-    is_new_package: bool,
+/// The PR template of typst/packages, used if the one of the upstream cannot
+/// be fetched.
+pub const DEFAULT_PR_TEMPLATE: &str = include_str!("pull_request_template.md");
+
+const PR_TEMPLATE_PATH: &str = ".github/pull_request_template.md";
+/// How long a fetched PR template is used before fetching it again
+const PR_TEMPLATE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The PR template of the upstream repository: cached for a day, fetched if
+/// stale, or the last cached one (then [`DEFAULT_PR_TEMPLATE`]) if fetching
+/// fails.
+pub async fn pr_template(upstream: &Upstream) -> String {
+    let cache = cache_dir().join(format!(
+        "pull_request_template-{}-{}-{}.md",
+        upstream.owner,
+        upstream.repo,
+        upstream.branch.replace('/', "_")
+    ));
+    let cached = std::fs::read_to_string(&cache).ok();
+    let fresh = std::fs::metadata(&cache)
+        .and_then(|m| m.modified())
+        .is_ok_and(|t| t.elapsed().is_ok_and(|age| age < PR_TEMPLATE_TTL));
+    if let (Some(template), true) = (&cached, fresh) {
+        return template.clone();
+    }
+    match fetch_pr_template(upstream).await {
+        Ok(template) => {
+            if let Err(e) =
+                std::fs::create_dir_all(cache_dir()).and_then(|_| std::fs::write(&cache, &template))
+            {
+                warn!("Failed to cache the PR template: {}", e);
+            }
+            template
+        }
+        Err(e) => {
+            warn!(
+                "Failed to fetch the PR template, using the {} one: {}",
+                if cached.is_some() {
+                    "cached"
+                } else {
+                    "built-in"
+                },
+                e
+            );
+            cached.unwrap_or_else(|| DEFAULT_PR_TEMPLATE.into())
+        }
+    }
+}
+
+async fn fetch_pr_template(upstream: &Upstream) -> Result<String> {
+    upstream
+        .client(None)?
+        .repos(&upstream.owner, &upstream.repo)
+        .get_content()
+        .path(PR_TEMPLATE_PATH)
+        .r#ref(&upstream.branch)
+        .send()
+        .await?
+        .items
+        .first()
+        .and_then(|item| item.decoded_content())
+        .ok_or(anyhow!("{} is not a file", PR_TEMPLATE_PATH))
+}
 
-    /// Please add a brief description of your package below and explain why you
-    /// think it is useful to others. If this is an update, please briefly say
-    /// what changed.
-    ///
-    /// Description: Explain what the package does and why it's useful.
-    desc: String,
+/// What is filled into the PR template.
+pub struct SubmissionMessage {
+    /// Ticks "a new package", otherwise "an update for a package"
+    pub is_new_package: bool,
+    /// Replaces the example after `Description:`
+    pub desc: String,
+    /// Ticks the item about the license of the template directory
+    pub has_template: bool,
+    /// Ticks the item about the keys of `typst.toml`
+    pub has_manifest_keys: bool,
+    /// Ticks the item about the `README.md`
+    pub has_readme: bool,
+    /// Ticks the item about the `LICENSE` file
+    pub has_license: bool,
 }
 
 impl SubmissionMessage {
-    pub fn to_string(&self, has_template: bool) -> String {
-        // TODO: maybe leave a interactive dialog for the user to fill in the
-        // description?
-        let template = "\n- [x] ensured that my package is licensed such that users can use and distribute the contents of its template directory without restriction, after modifying them through normal use.\n";
-        format!(
-            "I am submitting\n\
-            - [{}] a new package\n\
-            - [{}] an update for a package\n\n\
-            Description: {}\n\n\
-            I have read and followed the submission guidelines and, in particular, I\n\
-            - [x] selected a name that isn't the most obvious or canonical name for what the package does\n\
-            - [x] added a `typst.toml` file with all required keys\n\
-            - [x] added a `README.md` with documentation for my package\n\
-            - [x] have chosen a license and added a `LICENSE` file or linked one in my `README.md`\n\
-            - [x] tested my package locally on my system and it worked\n\
-            - [x] `exclude`d PDFs or README images, if any, but not the LICENSE\n{}",
-            if self.is_new_package { "x" } else { " " },
-            if !self.is_new_package { "x" } else { " " },
-            self.desc,
-            if has_template { template } else { "" }
-        )
+    /// Fill in the PR `template`. Items that typship verified are checked,
+    /// the others are asked about, defaulting to unchecked.
+    pub fn fill(&self, template: &str, prompter: &dyn Prompter) -> Result<String> {
+        let mut body = Vec::new();
+        let mut has_desc = false;
+        let mut unchecked = false;
+        for line in template.lines() {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let bullet = line.trim_start().get(..1).unwrap_or_default();
+            let item = ["- [ ] ", "* [ ] "]
+                .iter()
+                .find_map(|box_| line.trim_start().strip_prefix(box_));
+            if let Some(item) = item {
+                let verified = if item.contains("new package") {
+                    Some(self.is_new_package)
+                } else if item.contains("update") {
+                    Some(!self.is_new_package)
+                } else if item.contains("template") {
+                    Some(self.has_template)
+                } else if item.contains("`typst.toml`") && self.has_manifest_keys
+                    || item.contains("`LICENSE`") && self.has_license
+                    || item.contains("`README.md`") && !item.contains("LICENSE") && self.has_readme
+                {
+                    Some(true)
+                } else {
+                    None
+                };
+                let checked = match verified {
+                    Some(checked) => checked,
+                    None => {
+                        let checked = prompter.confirm(&format!("Checklist: {}", item), false)?;
+                        unchecked |= !checked;
+                        checked
+                    }
+                };
+                let mark = if checked { "x" } else { " " };
+                body.push(format!("{}{} [{}] {}", indent, bullet, mark, item));
+            } else if line.trim_start().starts_with("Description:") {
+                has_desc = true;
+                body.push(format!("Description: {}", self.desc));
+            } else {
+                body.push(line.to_string());
+            }
+        }
+        if !has_desc {
+            body.extend(["".into(), format!("Description: {}", self.desc)]);
+        }
+        if unchecked {
+            warn!("Some guideline items are not checked. Reviewers will likely ask about them.");
+        }
+        Ok(body.join("\n") + "\n")
     }
 }
 
-fn check_git_version() -> Result<bool> {
-    let output = Command::new("git").args(["--version"]).output()?;
+/// The section of a changelog about `version`: everything below the heading
+/// naming it (e.g. `## 0.2.0`, `## [v0.2.0] - 2024-01-01`) up to the next
+/// heading of the same or a higher level.
+pub fn changelog_section(changelog: &str, version: &PackageVersion) -> Option<String> {
+    let version = version.to_string();
+    fn heading(line: &str) -> Option<(usize, &str)> {
+        let level = line.chars().take_while(|c| *c == '#').count();
+        (level > 0 && line[level..].starts_with(' ')).then_some((level, &line[level..]))
+    }
+    let names_version = |title: &str| {
+        title
+            .split(|c: char| c.is_whitespace() || "[]()".contains(c))
+            .any(|word| word.strip_prefix('v').unwrap_or(word) == version)
+    };
+    let mut lines = changelog.lines();
+    let level = lines
+        .find_map(|line| heading(line).filter(|(_, title)| names_version(title)))?
+        .0;
+    let section = lines
+        .take_while(|line| heading(line).is_none_or(|(l, _)| l > level))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let section = section.trim();
+    (!section.is_empty()).then(|| section.to_string())
+}
+
+/// The changelog section about the version being published, if any.
+fn changelog_entry(package_dir: &Path, version: &PackageVersion) -> Option<String> {
+    ["CHANGELOG.md", "changelog.md", "CHANGES.md"]
+        .iter()
+        .find_map(|name| std::fs::read_to_string(package_dir.join(name)).ok())
+        .and_then(|changelog| changelog_section(&changelog, version))
+}
+
+/// The PR body: the `--message-file`, or the upstream PR template filled in
+/// and edited by the user.
+async fn submission_body(
+    manifest: &PackageManifest,
+    package_dir: &Path,
+    is_new_package: bool,
+    options: &PublishOptions,
+    prompter: &dyn Prompter,
+) -> Result<String> {
+    if let Some(path) = &options.message_file {
+        return std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the message file {}", path.display()));
+    }
+    let description = manifest
+        .package
+        .description
+        .clone()
+        .ok_or(anyhow!("Missing description"))?;
+    let desc = match is_new_package {
+        true => description.into(),
+        false => changelog_entry(package_dir, &manifest.package.version)
+            .unwrap_or_else(|| description.into()),
+    };
+    let msg = SubmissionMessage {
+        is_new_package,
+        desc,
+        has_template: manifest.template.is_some(),
+        has_manifest_keys: !manifest.package.authors.is_empty()
+            && manifest.package.license.is_some(),
+        has_readme: package_dir.join("README.md").is_file(),
+        has_license: !license_files(package_dir).is_empty(),
+    };
+    let body = msg.fill(&pr_template(&options.upstream).await, prompter)?;
+    prompter.edit("Edit the PR description", &body)
+}
 
-    if !output.status.success() {
-        bail!("Failed to check git version");
+/// Upload the package `files` to the submission branch, which holds the
+/// `remote` files (none for a new branch).
+#[allow(clippy::too_many_arguments)]
+async fn upload_files(
+    client: &Octocrab,
+    (user_login, repo_name): (&str, &str),
+    submission: &PackageSubmission,
+    package_dir: &Path,
+    files: &[PathBuf],
+    remote: &BTreeMap<PathBuf, String>,
+    token: &SecretString,
+    options: &PublishOptions,
+) -> Result<()> {
+    let message = format!(
+        "[Typship] {} package {}:{}",
+        if remote.is_empty() { "Add" } else { "Update" },
+        submission.name,
+        submission.version
+    );
+    match options.upload_method {
+        UploadMethod::Sparse if git::supports_sparse_checkout() => {
+            if remote.is_empty() {
+                init_package_dir(client, user_login, repo_name, submission, package_dir).await?;
+            }
+            upload_files_sparse_checkout(
+                user_login,
+                repo_name,
+                submission,
+                package_dir,
+                files,
+                token,
+                options,
+                &message,
+            )
+        }
+        UploadMethod::Sparse => {
+            info!("Git version does not support sparse-checkout, automatically switch to api upload method.");
+            upload_files_api(
+                client,
+                user_login,
+                repo_name,
+                submission,
+                package_dir,
+                files,
+                remote,
+            )
+            .await
+        }
+        UploadMethod::Api => {
+            upload_files_api(
+                client,
+                user_login,
+                repo_name,
+                submission,
+                package_dir,
+                files,
+                remote,
+            )
+            .await
+        }
+        UploadMethod::GitData => {
+            upload_files_git_data(
+                client,
+                user_login,
+                repo_name,
+                submission,
+                package_dir,
+                files,
+                remote,
+                &message,
+            )
+            .await
+        }
     }
+}
 
-    let version_str = String::from_utf8_lossy(&output.stdout);
-    let version_regex = Regex::new(r"git version (\d+)\.(\d+)\.(\d+).*")?;
+/// The SHA of an object created through the Git Data API.
+#[derive(Debug, Deserialize)]
+struct GitObject {
+    sha: String,
+}
 
-    if let Some(captures) = version_regex.captures(&version_str) {
-        let major: u32 = captures[1].parse()?;
-        let minor: u32 = captures[2].parse()?;
+#[derive(Debug, Deserialize)]
+struct GitCommit {
+    tree: GitObject,
+}
 
-        // sparse-checkout --cone requires Git 2.25+
-        Ok(major > 2 || (major == 2 && minor >= 25))
-    } else {
-        warn!("Could not parse git version, falling back to API upload");
-        Ok(false)
+/// Upload the files in a single commit through the Git Data API: a blob for
+/// each new or changed file, one tree on top of the branch's, and one commit
+/// the branch is moved to. Unlike the contents API, this handles files of any
+/// size up to the git blob limit.
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_files_git_data(
+    client: &Octocrab,
+    user_login: &str,
+    repo_name: &str,
+    submission: &PackageSubmission,
+    package_dir: &Path,
+    files: &[PathBuf],
+    remote: &BTreeMap<PathBuf, String>,
+    commit_message: &str,
+) -> Result<()> {
+    let route = format!("/repos/{}/{}/git", user_login, repo_name);
+    let branch = submission.branch_name();
+    let repo_path = |file: &Path| {
+        submission
+            .repo_path()
+            .join(file)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+
+    let parent = branch_head(client, user_login, repo_name, &branch).await?;
+    let base: GitCommit = client
+        .get(format!("{}/commits/{}", route, parent), None::<&()>)
+        .await?;
+    let mut tree = Vec::new();
+    for file in files {
+        let path = package_dir.join(file);
+        let content = std::fs::read(&path)?;
+        if remote.get(file) == Some(&git_blob_sha1(&content)) {
+            continue;
+        }
+        let blob: GitObject = client
+            .post(
+                format!("{}/blobs", route),
+                Some(&serde_json::json!({
+                    "content": BASE64_STANDARD.encode(&content),
+                    "encoding": "base64",
+                })),
+            )
+            .await?;
+        tree.push(serde_json::json!({
+            "path": repo_path(file),
+            "mode": git_file_mode(&path)?,
+            "type": "blob",
+            "sha": blob.sha,
+        }));
+        info!("Uploaded: {}", file.display());
+    }
+    for file in remote.keys().filter(|file| !files.contains(file)) {
+        // A null SHA removes the file from the base tree
+        tree.push(serde_json::json!({
+            "path": repo_path(file),
+            "mode": "100644",
+            "type": "blob",
+            "sha": null,
+        }));
+        info!("Removed: {}", file.display());
+    }
+    if tree.is_empty() {
+        return Ok(());
+    }
+
+    let tree: GitObject = client
+        .post(
+            format!("{}/trees", route),
+            Some(&serde_json::json!({ "base_tree": base.tree.sha, "tree": tree })),
+        )
+        .await?;
+    let commit: GitObject = client
+        .post(
+            format!("{}/commits", route),
+            Some(&serde_json::json!({
+                "message": commit_message,
+                "tree": tree.sha,
+                "parents": [parent],
+            })),
+        )
+        .await?;
+    client
+        .patch::<serde_json::Value, _, _>(
+            format!("{}/refs/heads/{}", route, branch),
+            Some(&serde_json::json!({ "sha": commit.sha, "force": false })),
+        )
+        .await?;
+    info!("Committed {}", commit.sha);
+    Ok(())
+}
+
+/// The mode of the file at `path` in a git tree: executable or not.
+fn git_file_mode(path: &Path) -> Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if std::fs::metadata(path)?.permissions().mode() & 0o111 != 0 {
+            return Ok("100755");
+        }
     }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok("100644")
 }
 
+/// Upload the files one commit at a time, skipping unchanged ones and
+/// removing `remote` files the package no longer has.
 async fn upload_files_api(
     client: &Octocrab,
     user_login: &str,
@@ -477,35 +1266,60 @@ async fn upload_files_api(
     submission: &PackageSubmission,
     package_dir: &Path,
     files: &[PathBuf],
+    remote: &BTreeMap<PathBuf, String>,
 ) -> Result<()> {
+    let repo = client.repos(user_login, repo_name);
+    let repo_path = |file: &Path| {
+        submission
+            .repo_path()
+            .join(file)
+            .to_string_lossy()
+            .into_owned()
+    };
     for file in files {
         let content = std::fs::read(package_dir.join(file))?;
-        client
-            .repos(user_login, repo_name)
-            .create_file(
-                submission
-                    .repo_path()
-                    .join(file)
-                    .to_string_lossy()
-                    .into_owned(),
+        let upload = match remote.get(file) {
+            None => repo.create_file(
+                repo_path(file),
                 format!("[Typship] Add {}", file.display()),
                 &content,
-            )
+            ),
+            Some(sha) if *sha == git_blob_sha1(&content) => continue,
+            Some(sha) => repo.update_file(
+                repo_path(file),
+                format!("[Typship] Update {}", file.display()),
+                &content,
+                sha,
+            ),
+        };
+        upload
             .branch(submission.branch_name())
             .send()
             .await
             .map(|_| info!("Uploaded: {}", file.display()))?;
     }
+    for (file, sha) in remote.iter().filter(|(file, _)| !files.contains(file)) {
+        repo.delete_file(
+            repo_path(file),
+            format!("[Typship] Remove {}", file.display()),
+            sha,
+        )
+        .branch(submission.branch_name())
+        .send()
+        .await
+        .map(|_| info!("Removed: {}", file.display()))?;
+    }
     Ok(())
 }
 
-async fn upload_files_sparse_checkout(
+/// Create the version directory in the new submission branch, so that it can
+/// be checked out sparsely.
+async fn init_package_dir(
     client: &Octocrab,
     user_login: &str,
     repo_name: &str,
     submission: &PackageSubmission,
     package_dir: &Path,
-    files: &[PathBuf],
 ) -> Result<()> {
     let typst_toml_content = std::fs::read(package_dir.join("typst.toml"))?;
 
@@ -523,73 +1337,42 @@ async fn upload_files_sparse_checkout(
         .branch(submission.branch_name())
         .send()
         .await?;
+    Ok(())
+}
 
-    let temp_dir = TempDir::new()?;
-    let temp_path = temp_dir.path();
+#[allow(clippy::too_many_arguments)]
+fn upload_files_sparse_checkout(
+    user_login: &str,
+    repo_name: &str,
+    submission: &PackageSubmission,
+    package_dir: &Path,
+    files: &[PathBuf],
+    token: &SecretString,
+    options: &PublishOptions,
+    commit_message: &str,
+) -> Result<()> {
+    let work_dir = WorkDir::new("publish", options.keep)?;
+    let temp_path = work_dir.path();
 
-    let fork_url = format!("https://github.com/{}/{}.git", user_login, repo_name);
+    let fork_url = options.upstream.git_url(user_login, repo_name);
     let target_path = submission.repo_path();
-
-    let output = Command::new("git")
-        .args([
-            "clone",
-            "--filter=blob:none",
-            "--no-checkout",
-            "--single-branch",
-            "--branch",
-            &submission.branch_name(),
-            &fork_url,
-            "repo",
-        ])
-        .current_dir(temp_path)
-        .output()?;
-
-    if !output.status.success() {
-        bail!(
-            "Failed to clone repository: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-
+    let branch = submission.branch_name();
     let repo_path = temp_path.join("repo");
 
-    let output = Command::new("git")
-        .args(["sparse-checkout", "init", "--cone"])
-        .current_dir(&repo_path)
-        .output()?;
-
-    if !output.status.success() {
-        bail!(
-            "Failed to initialize sparse-checkout: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-
-    let output = Command::new("git")
-        .args(["sparse-checkout", "set", &target_path.to_string_lossy()])
-        .current_dir(&repo_path)
-        .output()?;
-
-    if !output.status.success() {
-        bail!(
-            "Failed to set sparse-checkout patterns: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-
-    let output = Command::new("git")
-        .args(["checkout"])
-        .current_dir(&repo_path)
-        .output()?;
-
-    if !output.status.success() {
-        bail!(
-            "Failed to checkout files: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
+    let options = CloneOptions {
+        branch: Some(&branch),
+        shallow: true,
+        no_checkout: true,
+        token: Some(token.expose_secret()),
+    };
+    git::clone(&fork_url, &repo_path, options)?;
+    git::sparse_checkout(&repo_path, &target_path)?;
 
+    // Start over, so that files removed from the package are removed as well
     let local_target_dir = repo_path.join(&target_path);
+    if local_target_dir.exists() {
+        std::fs::remove_dir_all(&local_target_dir)?;
+    }
     std::fs::create_dir_all(&local_target_dir)?;
 
     for file in files {
@@ -603,45 +1386,8 @@ async fn upload_files_sparse_checkout(
         std::fs::copy(&src_path, &dst_path)?;
     }
 
-    let output = Command::new("git")
-        .args(["add", "."])
-        .current_dir(&repo_path)
-        .output()?;
-
-    if !output.status.success() {
-        bail!(
-            "Failed to add files to git: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
-
-    let commit_message = format!(
-        "[Typship] Add package {}:{}",
-        submission.name, submission.version
-    );
-    let output = Command::new("git")
-        .args(["commit", "-m", &commit_message])
-        .current_dir(&repo_path)
-        .output()?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if !stderr.contains("nothing to commit") {
-            bail!("Failed to commit files: {}", stderr);
-        }
-    }
-
-    let output = Command::new("git")
-        .args(["push", "origin", &submission.branch_name()])
-        .current_dir(&repo_path)
-        .output()?;
-
-    if !output.status.success() {
-        bail!(
-            "Failed to push to remote: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-    }
+    git::commit_path(&repo_path, &target_path, commit_message)?;
+    git::push(&repo_path, &branch, Some(token.expose_secret()))?;
 
     Ok(())
 }
@@ -1,15 +1,20 @@
+pub mod git;
+pub mod temp;
 pub mod walkers;
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
-use std::{env, fs};
+use std::{env, fs, io};
 
 use anyhow::{Context, Result};
 use log::info;
+use sha1::Sha1;
 use sha2::{Digest, Sha256};
-use typst_syntax::package::PackageManifest;
+use typst_syntax::package::{PackageManifest, PackageSpec};
 
 use crate::config::Config;
+use crate::model::receipt::Receipt;
 
 const DEFAULT_PACKAGES_SUBDIR: &str = "typst/packages"; // from typst-kit
 
@@ -29,6 +34,16 @@ pub fn config_file() -> &'static Path {
     CONFIG_PATH.as_path()
 }
 
+pub fn cache_dir() -> &'static Path {
+    static CACHE_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+        dirs::cache_dir()
+            .expect("Failed to get the cache directory")
+            .join(env!("CARGO_PKG_NAME"))
+    });
+
+    CACHE_DIR.as_path()
+}
+
 /// Should always return a valid config
 pub fn load_config() -> Result<Config> {
     if !config_file().exists() {
@@ -59,12 +74,39 @@ pub fn typst_local_dir() -> PathBuf {
         .join(DEFAULT_PACKAGES_SUBDIR)
 }
 
-pub fn temp_subdir(id: &str) -> PathBuf {
-    let mut path = env::temp_dir();
-    let hash = format!("{:x}", Sha256::digest(id.as_bytes()));
-    let truncated_hash = &hash[0..16];
-    path.push(format!("{}-{}", env!("CARGO_PKG_NAME"), truncated_hash));
-    path
+/// Create a symlink at `link` pointing to the directory `original`.
+pub fn symlink_dir(original: &Path, link: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(original, link)
+    }
+    #[cfg(windows)]
+    {
+        std::os::windows::fs::symlink_dir(original, link)
+    }
+}
+
+/// Unpack a gzip-compressed tarball, such as a package bundle, into `dest`.
+/// Entries escaping `dest` are skipped.
+pub fn unpack_archive(archive: impl io::Read, dest: &Path) -> Result<()> {
+    tar::Archive::new(flate2::read::GzDecoder::new(archive))
+        .unpack(dest)
+        .context("Failed to unpack the archive")
+}
+
+/// The directory containing `typst.toml` in an unpacked archive: either
+/// `dir` itself (the layout of package bundles), or its only subdirectory.
+pub fn package_root(dir: &Path) -> Result<PathBuf> {
+    if dir.join("typst.toml").is_file() {
+        return Ok(dir.to_path_buf());
+    }
+    let entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    if let [entry] = entries.as_slice() {
+        if entry.path().join("typst.toml").is_file() {
+            return Ok(entry.path());
+        }
+    }
+    anyhow::bail!("No `typst.toml` found in the archive")
 }
 
 pub fn read_manifest(package_dir: &Path) -> Result<PackageManifest> {
@@ -80,3 +122,141 @@ pub fn write_manifest(package_dir: &Path, manifest: &PackageManifest) -> Result<
         .context("Failed to write the package manifest file")?;
     Ok(())
 }
+
+/// Receipts of the packages typship placed in `packages_dir`. Namespaces
+/// cannot start with a dot, so this never clashes with a namespace.
+pub fn receipts_dir(packages_dir: &Path) -> PathBuf {
+    packages_dir.join(".typship").join("receipts")
+}
+
+pub fn receipt_file(packages_dir: &Path, spec: &PackageSpec) -> PathBuf {
+    receipts_dir(packages_dir)
+        .join(spec.namespace.as_str())
+        .join(spec.name.as_str())
+        .join(format!("{}.toml", spec.version))
+}
+
+pub fn read_receipt(packages_dir: &Path, spec: &PackageSpec) -> Result<Option<Receipt>> {
+    let path = receipt_file(packages_dir, spec);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let receipt = fs::read_to_string(&path).context("Failed to read the install receipt")?;
+    let receipt = toml::from_str(&receipt)
+        .with_context(|| format!("Failed to parse the install receipt {}", path.display()))?;
+    Ok(Some(receipt))
+}
+
+pub fn write_receipt(packages_dir: &Path, receipt: &Receipt) -> Result<()> {
+    let path = receipt_file(packages_dir, &receipt.spec());
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, toml::to_string_pretty(receipt)?)
+        .context("Failed to write the install receipt")?;
+    Ok(())
+}
+
+pub fn remove_receipt(packages_dir: &Path, spec: &PackageSpec) -> Result<()> {
+    let path = receipt_file(packages_dir, spec);
+    if path.is_file() {
+        fs::remove_file(&path).context("Failed to remove the install receipt")?;
+    }
+    Ok(())
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The object ID git gives a file with `content`.
+pub fn git_blob_sha1(content: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()));
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+/// SHA-256 of the files of the package in `dir` that `install` copies, keyed
+/// by their path relative to `dir`.
+pub fn package_file_hashes(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for entry in walkers::walker_install(dir)?.into_iter().flatten() {
+        let path = entry.path();
+        if path.is_file() {
+            let rel_path = path.strip_prefix(dir).unwrap();
+            files.insert(
+                rel_path.to_string_lossy().replace('\\', "/"),
+                sha256_file(path)?,
+            );
+        }
+    }
+    Ok(files)
+}
+
+/// SHA-256 of a whole package: the digest of a `<sha256>  <path>` line per
+/// file, sorted by path.
+pub fn tree_sha256(files: &BTreeMap<String, String>) -> String {
+    let mut hasher = Sha256::new();
+    for (path, hash) in files {
+        hasher.update(format!("{}  {}\n", hash, path));
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Total size of the files in `path`, without following symlinks.
+pub fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for u in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = u;
+    }
+    format!("{:.1} {}", size, unit)
+}
+
+/// Format seconds since the Unix epoch as `YYYY-MM-DD HH:MM` (UTC).
+pub fn format_timestamp(secs: u64) -> String {
+    // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let rem = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60
+    )
+}
@@ -0,0 +1,80 @@
+//! The git operations typship needs.
+//!
+//! By default they are run by the `git` binary. With the `native-git` feature,
+//! they run in-process with libgit2 instead, so no `git` installation is
+//! needed.
+
+use std::fmt;
+use std::io;
+
+#[cfg(not(feature = "native-git"))]
+mod cli;
+#[cfg(feature = "native-git")]
+mod native;
+
+#[cfg(not(feature = "native-git"))]
+pub use cli::*;
+#[cfg(feature = "native-git")]
+pub use native::*;
+
+pub type GitResult<T> = Result<T, GitError>;
+
+#[derive(Debug)]
+pub enum GitError {
+    /// The `git` binary could not be run
+    Unavailable(io::Error),
+    /// A tag, branch, or commit does not exist
+    RefNotFound(String),
+    /// An operation failed, with the message of git
+    Failed {
+        operation: &'static str,
+        message: String,
+    },
+}
+
+impl GitError {
+    fn failed(operation: &'static str, message: impl ToString) -> Self {
+        Self::Failed {
+            operation,
+            message: message.to_string().trim().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::Unavailable(e) => write!(f, "Failed to run git: {}", e),
+            GitError::RefNotFound(reference) => write!(f, "`{}` not found", reference),
+            GitError::Failed { operation, message } => {
+                write!(f, "Failed to {}: {}", operation, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GitError::Unavailable(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Options for [`clone`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CloneOptions<'a> {
+    /// Branch or tag to check out instead of the default branch. Only this
+    /// branch is fetched.
+    pub branch: Option<&'a str>,
+    /// Only fetch the latest commit
+    pub shallow: bool,
+    /// Leave the working tree empty, for a later [`sparse_checkout`]. The
+    /// `git` binary then also skips fetching file contents until they are
+    /// checked out (`--filter=blob:none`); libgit2 has no partial clones, so
+    /// with `native-git` every file of the fetched commits is downloaded.
+    pub no_checkout: bool,
+    /// Token to authenticate with, e.g. for a private fork
+    pub token: Option<&'a str>,
+}
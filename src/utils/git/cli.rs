@@ -0,0 +1,157 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use regex::Regex;
+
+use super::{CloneOptions, GitError, GitResult};
+
+fn git(dir: Option<&Path>, operation: &'static str, args: &[&str]) -> GitResult<Output> {
+    let mut command = Command::new("git");
+    command.args(args);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    run(command, operation)
+}
+
+fn run(mut command: Command, operation: &'static str) -> GitResult<Output> {
+    let output = command.output().map_err(GitError::Unavailable)?;
+    if !output.status.success() {
+        return Err(GitError::failed(
+            operation,
+            String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(output)
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Clone `url` into the empty directory `dest`.
+pub fn clone(url: &str, dest: &Path, options: CloneOptions) -> GitResult<()> {
+    let mut args = vec!["clone"];
+    if let Some(branch) = options.branch {
+        args.extend(["--single-branch", "--branch", branch]);
+    }
+    if options.shallow {
+        args.push("--depth=1");
+    }
+    if options.no_checkout {
+        args.extend(["--filter=blob:none", "--no-checkout"]);
+    }
+    let dest = dest.to_string_lossy();
+    args.extend(["--", url, &dest]);
+    let mut command = Command::new("git");
+    command.args(args);
+    authenticate(&mut command, options.token);
+    run(command, "clone").map(drop)
+}
+
+/// Check out a tag, commit, or branch (including remote branches).
+pub fn checkout(repo: &Path, reference: &str) -> GitResult<()> {
+    git(Some(repo), "checkout", &["checkout", reference])
+        .map(drop)
+        .map_err(|e| match e {
+            GitError::Failed { message, .. } if message.contains("did not match any") => {
+                GitError::RefNotFound(reference.to_string())
+            }
+            e => e,
+        })
+}
+
+/// The SHA of the commit checked out in `repo`.
+pub fn head_commit(repo: &Path) -> GitResult<String> {
+    git(Some(repo), "read HEAD", &["rev-parse", "HEAD"]).map(|o| stdout(&o))
+}
+
+/// The names of the tags in the remote repository at `url`.
+pub fn remote_tags(url: &str) -> GitResult<Vec<String>> {
+    let output = git(
+        None,
+        "list the tags",
+        &["ls-remote", "--tags", "--refs", url],
+    )?;
+    Ok(stdout(&output)
+        .lines()
+        .filter_map(|line| line.split_once("refs/tags/"))
+        .map(|(_, tag)| tag.to_string())
+        .collect())
+}
+
+/// Whether [`sparse_checkout`] is supported, which requires Git 2.25+.
+pub fn supports_sparse_checkout() -> bool {
+    let Ok(output) = git(None, "check the git version", &["--version"]) else {
+        return false;
+    };
+    let version_regex = Regex::new(r"git version (\d+)\.(\d+)").unwrap();
+    version_regex
+        .captures(&stdout(&output))
+        .and_then(|c| Some((c[1].parse::<u32>().ok()?, c[2].parse::<u32>().ok()?)))
+        .is_some_and(|(major, minor)| major > 2 || (major == 2 && minor >= 25))
+}
+
+/// Check out only `path` of a repository cloned with `no_checkout`.
+pub fn sparse_checkout(repo: &Path, path: &Path) -> GitResult<()> {
+    let path = path.to_string_lossy();
+    git(
+        Some(repo),
+        "initialize sparse-checkout",
+        &["sparse-checkout", "init", "--cone"],
+    )?;
+    git(
+        Some(repo),
+        "set sparse-checkout patterns",
+        &["sparse-checkout", "set", &path],
+    )?;
+    git(Some(repo), "checkout files", &["checkout"]).map(drop)
+}
+
+/// Commit every change below `path`, including deletions. Returns `false` if
+/// there was nothing to commit.
+pub fn commit_path(repo: &Path, path: &Path, message: &str) -> GitResult<bool> {
+    git(
+        Some(repo),
+        "add files to git",
+        &["add", "--all", "--", &path.to_string_lossy()],
+    )?;
+    let staged = git(
+        Some(repo),
+        "list changes",
+        &["diff", "--cached", "--name-only"],
+    )?;
+    if stdout(&staged).is_empty() {
+        return Ok(false);
+    }
+    git(Some(repo), "commit files", &["commit", "-m", message]).map(|_| true)
+}
+
+/// Push `branch` to `origin`, authenticating with `token` if given, or else
+/// with the credentials of the user's git setup.
+pub fn push(repo: &Path, branch: &str, token: Option<&str>) -> GitResult<()> {
+    let mut command = Command::new("git");
+    command.args(["push", "origin", branch]).current_dir(repo);
+    authenticate(&mut command, token);
+    run(command, "push to remote").map(drop)
+}
+
+/// Make `command` send `token` with its HTTP requests, if given.
+fn authenticate(command: &mut Command, token: Option<&str>) {
+    let Some(token) = token else {
+        return;
+    };
+    // Set in the environment rather than with `-c`, so that the token does
+    // not show up in the process list
+    let credentials = BASE64_STANDARD.encode(format!("x-access-token:{}", token));
+    command
+        .env("GIT_CONFIG_COUNT", "1")
+        .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+        .env(
+            "GIT_CONFIG_VALUE_0",
+            format!("Authorization: Basic {}", credentials),
+        )
+        // A rejected token fails instead of asking for other credentials
+        .env("GIT_TERMINAL_PROMPT", "0");
+}
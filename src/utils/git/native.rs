@@ -0,0 +1,212 @@
+use std::path::Path;
+
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    Cred, CredentialType, Direction, FetchOptions, IndexAddOption, PushOptions, Remote,
+    RemoteCallbacks, Repository, Signature,
+};
+
+use super::{CloneOptions, GitError, GitResult};
+
+impl From<git2::Error> for GitError {
+    fn from(e: git2::Error) -> Self {
+        GitError::failed("run git", e.message())
+    }
+}
+
+fn failed(operation: &'static str) -> impl Fn(git2::Error) -> GitError {
+    move |e| GitError::failed(operation, e.message())
+}
+
+/// Credentials from `token`, or else from the git credential helpers.
+fn callbacks(token: Option<&str>) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if let Some(token) = token {
+            if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                return Cred::userpass_plaintext("x-access-token", token);
+            }
+        }
+        let config = git2::Config::open_default()?;
+        Cred::credential_helper(&config, url, username)
+    });
+    callbacks
+}
+
+/// Clone `url` into the empty directory `dest`.
+pub fn clone(url: &str, dest: &Path, options: CloneOptions) -> GitResult<()> {
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(callbacks(options.token));
+    // The local transport of libgit2 does not support shallow fetches
+    let local = url.starts_with("file://") || Path::new(url).exists();
+    if options.shallow && !local {
+        fetch.depth(1);
+    }
+    let Some(branch) = options.branch else {
+        let mut builder = RepoBuilder::new();
+        builder.fetch_options(fetch);
+        if options.no_checkout {
+            let mut checkout = CheckoutBuilder::new();
+            checkout.dry_run();
+            builder.with_checkout(checkout);
+        }
+        builder.clone(url, dest).map_err(failed("clone"))?;
+        return Ok(());
+    };
+
+    // `RepoBuilder::branch` only accepts branches, so a single branch or tag
+    // is fetched by hand
+    let repo = Repository::init(dest)?;
+    let mut remote = repo.remote("origin", url)?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(callbacks(options.token)), None)
+        .map_err(failed("clone"))?;
+    let heads = connection
+        .list()?
+        .iter()
+        .map(|head| head.name().to_string())
+        .collect::<Vec<_>>();
+    drop(connection);
+    let is_branch = heads.contains(&format!("refs/heads/{}", branch));
+    let refspec = if is_branch {
+        format!("+refs/heads/{0}:refs/remotes/origin/{0}", branch)
+    } else if heads.contains(&format!("refs/tags/{}", branch)) {
+        format!("+refs/tags/{0}:refs/tags/{0}", branch)
+    } else {
+        return Err(GitError::RefNotFound(branch.to_string()));
+    };
+    remote
+        .fetch(&[refspec], Some(&mut fetch), None)
+        .map_err(failed("clone"))?;
+
+    if is_branch {
+        let remote_ref = format!("refs/remotes/origin/{}", branch);
+        let commit = repo.find_reference(&remote_ref)?.peel_to_commit()?;
+        let mut local = repo.branch(branch, &commit, true)?;
+        local.set_upstream(Some(&format!("origin/{}", branch)))?;
+        repo.set_head(&format!("refs/heads/{}", branch))?;
+    } else {
+        let tag_ref = format!("refs/tags/{}", branch);
+        let commit = repo.find_reference(&tag_ref)?.peel_to_commit()?;
+        repo.set_head_detached(commit.id())?;
+    }
+    if !options.no_checkout {
+        let mut checkout = CheckoutBuilder::new();
+        checkout.force();
+        repo.checkout_head(Some(&mut checkout))
+            .map_err(failed("checkout"))?;
+    }
+    Ok(())
+}
+
+/// Check out a tag, commit, or branch (including remote branches).
+pub fn checkout(repo: &Path, reference: &str) -> GitResult<()> {
+    let repo = Repository::open(repo)?;
+    let (object, reference) = repo
+        .revparse_ext(reference)
+        .or_else(|_| repo.revparse_ext(&format!("origin/{}", reference)))
+        .map_err(|_| GitError::RefNotFound(reference.to_string()))?;
+    let mut options = CheckoutBuilder::new();
+    options.safe();
+    repo.checkout_tree(&object, Some(&mut options))
+        .map_err(failed("checkout"))?;
+    match reference.as_ref().and_then(|r| r.name()) {
+        Some(name) if name.starts_with("refs/heads/") => repo.set_head(name),
+        _ => repo.set_head_detached(object.peel_to_commit()?.id()),
+    }
+    .map_err(failed("checkout"))
+}
+
+/// The SHA of the commit checked out in `repo`.
+pub fn head_commit(repo: &Path) -> GitResult<String> {
+    let repo = Repository::open(repo)?;
+    let commit = repo.head()?.peel_to_commit()?;
+    Ok(commit.id().to_string())
+}
+
+/// The names of the tags in the remote repository at `url`.
+pub fn remote_tags(url: &str) -> GitResult<Vec<String>> {
+    let mut remote = Remote::create_detached(url)?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(callbacks(None)), None)
+        .map_err(failed("list the tags"))?;
+    Ok(connection
+        .list()?
+        .iter()
+        .filter_map(|head| head.name().strip_prefix("refs/tags/"))
+        .filter(|tag| !tag.ends_with("^{}"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Always supported in-process.
+pub fn supports_sparse_checkout() -> bool {
+    true
+}
+
+/// Check out only `path` of a repository cloned with `no_checkout`.
+pub fn sparse_checkout(repo: &Path, path: &Path) -> GitResult<()> {
+    let repo = Repository::open(repo)?;
+    let mut options = CheckoutBuilder::new();
+    options.force().path(path);
+    repo.checkout_head(Some(&mut options))
+        .map_err(failed("checkout files"))
+}
+
+/// Commit every change below `path`, including deletions. Returns `false` if
+/// there was nothing to commit.
+pub fn commit_path(repo: &Path, path: &Path, message: &str) -> GitResult<bool> {
+    let repo = Repository::open(repo)?;
+    let parent = repo.head()?.peel_to_commit()?;
+    // Start from `HEAD`, as a sparse checkout leaves the rest of the index
+    // empty
+    let mut index = repo.index()?;
+    index.read_tree(&parent.tree()?)?;
+    index
+        .add_all([path], IndexAddOption::DEFAULT, None)
+        .map_err(failed("add files to git"))?;
+    index
+        .update_all([path], None)
+        .map_err(failed("add files to git"))?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    if tree.id() == parent.tree_id() {
+        return Ok(false);
+    }
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now(env!("CARGO_PKG_NAME"), "typship@users.noreply.github.com"))?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &[&parent],
+    )
+    .map_err(failed("commit files"))?;
+    Ok(true)
+}
+
+/// Push `branch` to `origin`, authenticating with `token` if given.
+pub fn push(repo: &Path, branch: &str, token: Option<&str>) -> GitResult<()> {
+    let repo = Repository::open(repo)?;
+    let mut remote = repo.find_remote("origin")?;
+    let mut rejection = None;
+    let mut callbacks = callbacks(token);
+    callbacks.push_update_reference(|_, status| {
+        rejection = status.map(str::to_string);
+        Ok(())
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+    remote
+        .push(&[refspec], Some(&mut options))
+        .map_err(failed("push to remote"))?;
+    drop(options);
+    match rejection {
+        Some(message) => Err(GitError::failed("push to remote", message)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::{env, io};
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use tempfile::TempDir;
+
+/// Leftovers younger than this are never swept, so that a directory is not
+/// taken between its creation and the locking of its lock file.
+const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// A uniquely named scratch directory in the system temp directory, removed
+/// when dropped unless `keep` is set.
+///
+/// While it exists, an advisory lock is held on a sibling `<dir>.lock` file.
+/// Directories whose lock is no longer held (e.g. after the process was
+/// killed) are swept by later runs.
+pub struct WorkDir {
+    dir: Option<TempDir>,
+    lock: Option<(File, PathBuf)>,
+    keep: bool,
+}
+
+impl WorkDir {
+    /// Create a directory named `typship-<purpose>-<random>`.
+    pub fn new(purpose: &str, keep: bool) -> Result<Self> {
+        sweep_stale();
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("{}-{}-", env!("CARGO_PKG_NAME"), purpose))
+            .tempdir()
+            .context("Failed to create a temporary directory")?;
+        let lock_path = lock_path(dir.path());
+        let lock = File::create(&lock_path)?;
+        lock.lock()?;
+        debug!("Working in {}", dir.path().display());
+        Ok(Self {
+            dir: Some(dir),
+            lock: Some((lock, lock_path)),
+            keep,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.as_ref().unwrap().path()
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        if let Some(dir) = self.dir.take() {
+            if self.keep {
+                let path = dir.keep();
+                info!("Kept the working directory {}", path.display());
+            } else if let Err(e) = dir.close() {
+                warn!("Failed to remove the working directory: {}", e);
+            }
+        }
+        // Without the lock file, a kept directory is never swept
+        if let Some((lock, lock_path)) = self.lock.take() {
+            drop(lock);
+            let _ = fs::remove_file(lock_path);
+        }
+    }
+}
+
+fn lock_path(dir: &Path) -> PathBuf {
+    let mut path = dir.as_os_str().to_owned();
+    path.push(".lock");
+    path.into()
+}
+
+/// Remove the work directories left behind by runs that did not clean up.
+fn sweep_stale() {
+    let prefix = concat!(env!("CARGO_PKG_NAME"), "-");
+    let Ok(entries) = fs::read_dir(env::temp_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if !name.starts_with(prefix) || !name.ends_with(".lock") {
+            continue;
+        }
+        let lock_path = entry.path();
+        if let Err(e) = sweep(&lock_path) {
+            debug!("Skipped sweeping {}: {}", lock_path.display(), e);
+        }
+    }
+}
+
+fn sweep(lock_path: &Path) -> io::Result<()> {
+    let lock = File::open(lock_path)?;
+    let age = SystemTime::now()
+        .duration_since(lock.metadata()?.modified()?)
+        .unwrap_or_default();
+    if age < STALE_AFTER {
+        return Ok(());
+    }
+    // Fails if the owner is still running
+    lock.try_lock()?;
+    let dir = lock_path.with_extension("");
+    debug!("Removing stale working directory {}", dir.display());
+    if dir.is_dir() {
+        fs::remove_dir_all(&dir)?;
+    }
+    drop(lock);
+    fs::remove_file(lock_path)
+}
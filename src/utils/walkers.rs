@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use glob::Pattern;
@@ -14,6 +14,20 @@ pub fn walker_publish(root: &Path) -> Walk {
         .build()
 }
 
+/// Directories below `root` (including itself) containing a `typst.toml`,
+/// sorted. Hidden and ignored directories are skipped.
+pub fn find_packages(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = WalkBuilder::new(root)
+        .standard_filters(true)
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_name() == "typst.toml" && entry.path().is_file())
+        .filter_map(|entry| entry.path().parent().map(Path::to_path_buf))
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
 /// `.typstignore` and `package.excludes`
 pub fn walker_install(
     root: &Path,
@@ -0,0 +1,52 @@
+mod common;
+
+use std::fs;
+
+use common::{bundle, walker_test_path};
+use typship::commands::install::validate_package;
+use typship::utils::{package_file_hashes, package_root, tree_sha256, unpack_archive};
+
+#[test]
+fn test_unpack_bundle() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    unpack_archive(bundle("")?.as_slice(), dir.path())?;
+    let root = package_root(dir.path())?;
+    assert_eq!(root, dir.path());
+    let manifest = validate_package(&root)?;
+    assert_eq!(manifest.package.name, "walker-test");
+    Ok(())
+}
+
+#[test]
+fn test_unpack_nested() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    unpack_archive(bundle("walker-test-0.1.0")?.as_slice(), dir.path())?;
+    let root = package_root(dir.path())?;
+    assert_eq!(root, dir.path().join("walker-test-0.1.0"));
+
+    fs::remove_file(root.join("src/lib.typ"))?;
+    assert!(validate_package(&root).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_unpack_invalid() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    assert!(unpack_archive(&b"not an archive"[..], dir.path()).is_err());
+    assert!(package_root(dir.path()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_tree_sha256() -> anyhow::Result<()> {
+    // The bundle holds the same package files as the directory
+    let dir = tempfile::tempdir()?;
+    unpack_archive(bundle("walker-test-0.1.0")?.as_slice(), dir.path())?;
+    let root = package_root(dir.path())?;
+    let expected = tree_sha256(&package_file_hashes(&walker_test_path())?);
+    assert_eq!(tree_sha256(&package_file_hashes(&root)?), expected);
+
+    fs::write(root.join("src/lib.typ"), "changed")?;
+    assert_ne!(tree_sha256(&package_file_hashes(&root)?), expected);
+    Ok(())
+}
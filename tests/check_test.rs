@@ -0,0 +1,81 @@
+use std::fs;
+use std::path::Path;
+
+use typship::commands::check::{diagnose, Severity};
+
+fn write_package(dir: &Path, manifest: &str) -> anyhow::Result<()> {
+    fs::write(dir.join("typst.toml"), manifest)?;
+    fs::create_dir_all(dir.join("src"))?;
+    fs::write(dir.join("src/lib.typ"), "#let x = 1\n")?;
+    fs::write(dir.join("README.md"), "# foo\n")?;
+    fs::write(dir.join("LICENSE"), "MIT\n")?;
+    Ok(())
+}
+
+fn messages(dir: &Path, severity: Severity) -> anyhow::Result<Vec<String>> {
+    Ok(diagnose(dir)?
+        .into_iter()
+        .filter(|d| d.severity == severity)
+        .map(|d| d.message)
+        .collect())
+}
+
+#[test]
+fn test_check_valid() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    write_package(
+        dir.path(),
+        r#"
+[package]
+name = "foo"
+version = "0.1.0"
+entrypoint = "src/lib.typ"
+authors = ["someone"]
+license = "MIT"
+description = "Does foo."
+repository = "https://example.com/foo"
+compiler = "0.12.0"
+"#,
+    )?;
+    assert!(diagnose(dir.path())?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_check_errors() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    write_package(
+        dir.path(),
+        r#"
+[package]
+name = "foo"
+version = "0.1.0"
+entrypoint = "src/main.typ"
+authors = []
+description = "Does foo"
+exclude = ["LICENSE", "*.pdf"]
+
+[template]
+path = "template"
+entrypoint = "main.typ"
+"#,
+    )?;
+    fs::write(dir.path().join("manual.pdf"), "")?;
+
+    let errors = messages(dir.path(), Severity::Error)?;
+    assert_eq!(
+        errors,
+        [
+            "No `authors` in `typst.toml`",
+            "No `license` in `typst.toml`",
+            "The entrypoint `src/main.typ` does not exist or is excluded",
+            "`LICENSE` must not be excluded",
+            "The template directory `template` does not exist or is excluded",
+            "No `thumbnail` for the template",
+        ]
+    );
+    let warnings = messages(dir.path(), Severity::Warning)?;
+    assert_eq!(warnings.len(), 3);
+    assert!(!warnings.iter().any(|w| w.contains("manual.pdf")));
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use secrecy::ExposeSecret;
+use typship::regs::credentials::{token_from_helper, universe_token_in, TokenSource};
+
+#[test]
+fn test_token_precedence() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let token_file = dir.path().join("token");
+    fs::write(&token_file, "from-file\n")?;
+    let mut vars = HashMap::from([
+        ("TYPSHIP_UNIVERSE_TOKEN", "from-typship-env"),
+        ("GITHUB_TOKEN", "from-github-env"),
+    ]);
+    let token = |token_file: Option<&Path>, vars: &HashMap<&str, &str>| {
+        universe_token_in(token_file, "github.com", |var| {
+            vars.get(var).map(|value| value.to_string())
+        })
+    };
+
+    let (token_found, source) = token(Some(&token_file), &vars)?.unwrap();
+    assert_eq!(token_found.expose_secret(), "from-file");
+    assert!(matches!(source, TokenSource::File(_)));
+
+    let (token_found, source) = token(None, &vars)?.unwrap();
+    assert_eq!(token_found.expose_secret(), "from-typship-env");
+    assert!(matches!(source, TokenSource::Env("TYPSHIP_UNIVERSE_TOKEN")));
+
+    vars.insert("TYPSHIP_UNIVERSE_TOKEN", "");
+    let (token_found, _) = token(None, &vars)?.unwrap();
+    assert_eq!(token_found.expose_secret(), "from-github-env");
+
+    fs::write(&token_file, "")?;
+    assert!(token(Some(&token_file), &vars).is_err());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_credential_helper() -> anyhow::Result<()> {
+    // Echoes the requested host back as the password
+    let helper = r#"sh -c 'test "$1" = get && sed -n "s/^host=/password=/p"' --"#;
+    assert_eq!(
+        token_from_helper(helper, "github.com")?.as_deref(),
+        Some("github.com")
+    );
+    assert_eq!(token_from_helper("true", "github.com")?, None);
+    assert!(token_from_helper("false", "github.com").is_err());
+    Ok(())
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use std::fs;
+use std::str::FromStr;
+
+use common::{bundle, walker_test_path, MockServer, Response};
+use typship::commands::download::{download_bundle, verify_packages};
+use typship::model::receipt::Source;
+use typship::prompt::DenyPrompter;
+use typship::utils::walkers::find_packages;
+use typship::utils::{package_file_hashes, read_receipt, tree_sha256};
+use typst_syntax::package::PackageVersion;
+
+/// Serve the `walker-test:0.1.0` bundle under the URLs of a few versions and
+/// names, answering 404 to anything else.
+fn mock_registry() -> anyhow::Result<MockServer> {
+    let bundle = bundle("")?;
+    Ok(MockServer::start(move |request| {
+        let found = [
+            "/preview/walker-test-0.1.0.tar.gz",
+            "/preview/walker-test-0.2.0.tar.gz",
+            "/preview/other-0.1.0.tar.gz",
+        ]
+        .contains(&request.path.as_str());
+        if found {
+            Response::ok(bundle.clone())
+        } else {
+            Response::new("404 Not Found")
+        }
+    }))
+}
+
+#[test]
+fn test_download_bundle() -> anyhow::Result<()> {
+    let registry = mock_registry()?.url;
+    let packages = tempfile::tempdir()?;
+    let tree_hash = tree_sha256(&package_file_hashes(&walker_test_path())?);
+
+    let spec = "@preview/walker-test:0.1.0".parse().unwrap();
+    download_bundle(
+        packages.path(),
+        &spec,
+        &registry,
+        Some(&tree_hash),
+        "vendor",
+        &DenyPrompter,
+    )?;
+    let installed = packages.path().join("vendor/walker-test/0.1.0");
+    assert!(installed.join("src/lib.typ").is_file());
+    assert!(!installed.join("excludes_test.txt").exists());
+    let receipt = read_receipt(
+        packages.path(),
+        &"@vendor/walker-test:0.1.0".parse().unwrap(),
+    )?
+    .unwrap();
+    assert!(matches!(
+        receipt.source,
+        Source::Registry { url } if url == format!("{}/preview/walker-test-0.1.0.tar.gz", registry)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_download_bundle_rejected() -> anyhow::Result<()> {
+    let registry = mock_registry()?.url;
+    let packages = tempfile::tempdir()?;
+    let download = |spec: &str, sha256: Option<&str>| {
+        download_bundle(
+            packages.path(),
+            &spec.parse().unwrap(),
+            &registry,
+            sha256,
+            "vendor",
+            &DenyPrompter,
+        )
+        .unwrap_err()
+        .to_string()
+    };
+
+    assert_eq!(
+        download("@preview/missing:0.1.0", None),
+        format!("`@preview/missing:0.1.0` is not published at {}", registry)
+    );
+    assert_eq!(
+        download("@preview/other:0.1.0", None),
+        "Expected package `other`, but found `walker-test:0.1.0`"
+    );
+    assert_eq!(
+        download("@preview/walker-test:0.2.0", None),
+        "Expected version 0.2.0, but found `walker-test:0.1.0`"
+    );
+    assert!(download("@preview/walker-test:0.1.0", Some("00"))
+        .starts_with("The SHA-256 of `walker-test:0.1.0` is "));
+    // Nothing is installed after a mismatch
+    assert!(!packages.path().join("vendor").exists());
+    Ok(())
+}
+
+#[test]
+fn test_monorepo_tag_version() -> anyhow::Result<()> {
+    let repo = tempfile::tempdir()?;
+    for (dir, name, version) in [("a", "tpl", "0.3.0"), ("b", "other", "1.0.0")] {
+        let package_dir = repo.path().join("packages").join(dir);
+        fs::create_dir_all(&package_dir)?;
+        fs::write(
+            package_dir.join("typst.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"{}\"\nentrypoint = \"lib.typ\"\n",
+                name, version
+            ),
+        )?;
+        fs::write(package_dir.join("lib.typ"), "")?;
+    }
+    let packages = find_packages(repo.path());
+    assert_eq!(packages.len(), 2);
+
+    // The tag `v0.3.0` only pins the version when a single package is picked
+    let version = Some(PackageVersion::from_str("0.3.0").unwrap());
+    verify_packages(&packages, None, version, None)?;
+    verify_packages(&packages[..1], None, version, None)?;
+    assert!(verify_packages(&packages[1..], None, version, None).is_err());
+    assert!(verify_packages(&packages, None, None, Some("00")).is_err());
+    Ok(())
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use common::{MockServer, Response};
+use typship::utils::git::{self, CloneOptions, GitError};
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// A repository with `v0.1.0` and `v0.2.0` tags, and a `dev` branch.
+fn fixture(dir: &Path) -> String {
+    run(dir, &["init", "-q", "-b", "main"]);
+    for version in ["0.1.0", "0.2.0"] {
+        fs::create_dir_all(dir.join("pkg")).unwrap();
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        fs::write(dir.join("pkg/version"), version).unwrap();
+        fs::write(dir.join("docs/README.md"), version).unwrap();
+        run(dir, &["add", "."]);
+        run(dir, &["commit", "-q", "-m", version]);
+        run(dir, &["tag", &format!("v{}", version)]);
+    }
+    run(dir, &["branch", "dev", "v0.1.0"]);
+    format!("file://{}", dir.display())
+}
+
+#[test]
+fn test_clone_and_checkout() {
+    let temp = tempfile::tempdir().unwrap();
+    let origin = temp.path().join("origin");
+    fs::create_dir(&origin).unwrap();
+    let url = fixture(&origin);
+
+    let mut tags = git::remote_tags(&url).unwrap();
+    tags.sort();
+    assert_eq!(tags, ["v0.1.0", "v0.2.0"]);
+
+    let tagged = temp.path().join("tagged");
+    let options = CloneOptions {
+        branch: Some("v0.1.0"),
+        shallow: true,
+        ..Default::default()
+    };
+    git::clone(&url, &tagged, options).unwrap();
+    assert_eq!(
+        fs::read_to_string(tagged.join("pkg/version")).unwrap(),
+        "0.1.0"
+    );
+    assert_eq!(
+        git::head_commit(&tagged).unwrap(),
+        run(&origin, &["rev-parse", "v0.1.0"])
+    );
+
+    let full = temp.path().join("full");
+    git::clone(&url, &full, CloneOptions::default()).unwrap();
+    assert_eq!(
+        fs::read_to_string(full.join("pkg/version")).unwrap(),
+        "0.2.0"
+    );
+    git::checkout(&full, "dev").unwrap();
+    assert_eq!(
+        fs::read_to_string(full.join("pkg/version")).unwrap(),
+        "0.1.0"
+    );
+    assert!(matches!(
+        git::checkout(&full, "missing"),
+        Err(GitError::RefNotFound(_))
+    ));
+}
+
+#[test]
+fn test_sparse_commit_and_push() {
+    let temp = tempfile::tempdir().unwrap();
+    let origin = temp.path().join("origin");
+    fs::create_dir(&origin).unwrap();
+    fixture(&origin);
+    let bare = temp.path().join("bare.git");
+    run(
+        temp.path(),
+        &[
+            "clone",
+            "-q",
+            "--bare",
+            &origin.to_string_lossy(),
+            "bare.git",
+        ],
+    );
+    let url = format!("file://{}", bare.display());
+
+    let repo = temp.path().join("repo");
+    let options = CloneOptions {
+        branch: Some("dev"),
+        no_checkout: true,
+        ..Default::default()
+    };
+    git::clone(&url, &repo, options).unwrap();
+    // The committer of both backends
+    run(&repo, &["config", "user.name", "Test"]);
+    run(&repo, &["config", "user.email", "test@example.com"]);
+    git::sparse_checkout(&repo, Path::new("pkg")).unwrap();
+    assert!(repo.join("pkg/version").is_file());
+    assert!(!repo.join("docs").exists());
+
+    assert!(!git::commit_path(&repo, Path::new("pkg"), "Nothing").unwrap());
+    fs::write(repo.join("pkg/version"), "0.1.1").unwrap();
+    fs::write(repo.join("pkg/new"), "new").unwrap();
+    assert!(git::commit_path(&repo, Path::new("pkg"), "Update").unwrap());
+    git::push(&repo, "dev", None).unwrap();
+
+    // Files outside the sparse checkout are untouched
+    let files = run(&bare, &["ls-tree", "-r", "--name-only", "dev"]);
+    assert_eq!(files, "docs/README.md\npkg/new\npkg/version");
+    assert_eq!(run(&bare, &["show", "dev:pkg/version"]), "0.1.1");
+    assert_eq!(run(&bare, &["log", "-1", "--format=%s", "dev"]), "Update");
+}
+
+/// Answer every request with 401 unless it is authenticated, and with 403
+/// otherwise.
+fn mock_auth_server() -> MockServer {
+    MockServer::start(|request| match request.header("authorization") {
+        Some(_) => Response::new("403 Forbidden"),
+        None => {
+            Response::new("401 Unauthorized").header("WWW-Authenticate", "Basic realm=\"test\"")
+        }
+    })
+}
+
+#[test]
+fn test_token_auth() {
+    let temp = tempfile::tempdir().unwrap();
+    fixture(temp.path());
+    let server = mock_auth_server();
+    let url = format!("{}/o/r.git", server.url);
+    run(temp.path(), &["remote", "add", "origin", &url]);
+    // `x-access-token:secret`
+    let expected = "Basic eC1hY2Nlc3MtdG9rZW46c2VjcmV0";
+    let authorizations = || {
+        server
+            .requests()
+            .iter()
+            .filter_map(|r| r.header("authorization").map(str::to_string))
+            .collect::<Vec<_>>()
+    };
+
+    // The server refuses the clone and the push, but only after seeing the
+    // token
+    let options = CloneOptions {
+        token: Some("secret"),
+        ..Default::default()
+    };
+    assert!(git::clone(&url, &temp.path().join("clone"), options).is_err());
+    let headers = authorizations();
+    assert!(!headers.is_empty());
+    assert!(headers.iter().all(|h| h == expected), "{:?}", headers);
+    let cloned = headers.len();
+
+    assert!(git::push(temp.path(), "main", Some("secret")).is_err());
+    let headers = authorizations();
+    assert!(headers.len() > cloned);
+    assert!(headers.iter().all(|h| h == expected), "{:?}", headers);
+}
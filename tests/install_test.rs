@@ -0,0 +1,310 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use typship::commands::install::{install_from, InstallAs, InstallOptions, LinkMode};
+use typship::model::receipt::{InstallKind, Source};
+use typship::prompt::{DenyPrompter, ScriptedPrompter};
+use typship::utils::{read_manifest, read_receipt, sha256_file};
+
+const MANIFEST: &str = r#"[package]
+name = "foo"
+version = "0.1.0"
+entrypoint = "lib.typ"
+
+[template]
+path = "template"
+entrypoint = "main.typ"
+"#;
+
+fn write_package(dir: &Path, lib: &str) -> anyhow::Result<()> {
+    fs::write(dir.join("typst.toml"), MANIFEST)?;
+    fs::write(dir.join("lib.typ"), lib)?;
+    fs::create_dir_all(dir.join("template"))?;
+    fs::write(
+        dir.join("template/main.typ"),
+        "#import \"@preview/foo:0.1.0\": *\n",
+    )?;
+    Ok(())
+}
+
+fn source(dir: &Path) -> Source {
+    Source::Path {
+        path: dir.to_path_buf(),
+    }
+}
+
+/// Names of the entries in `dir`, sorted.
+fn entries(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut names = fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    names.sort();
+    Ok(names)
+}
+
+#[test]
+fn test_install_fresh() -> anyhow::Result<()> {
+    let src = tempfile::tempdir()?;
+    let packages = tempfile::tempdir()?;
+    write_package(src.path(), "#let x = 1\n")?;
+
+    let options = InstallOptions::new("local");
+    install_from(
+        packages.path(),
+        src.path(),
+        &options,
+        source(src.path()),
+        &DenyPrompter,
+    )?;
+    let installed = packages.path().join("local/foo/0.1.0");
+    assert_eq!(
+        fs::read_to_string(installed.join("lib.typ"))?,
+        "#let x = 1\n"
+    );
+    assert!(installed.join("template/main.typ").is_file());
+    assert_eq!(entries(&packages.path().join("local/foo"))?, ["0.1.0"]);
+
+    let receipt = read_receipt(packages.path(), &"@local/foo:0.1.0".parse().unwrap())?.unwrap();
+    assert_eq!(
+        receipt.files.keys().collect::<Vec<_>>(),
+        ["lib.typ", "template/main.typ", "typst.toml"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_install_overwrite() -> anyhow::Result<()> {
+    let src = tempfile::tempdir()?;
+    let packages = tempfile::tempdir()?;
+    let options = InstallOptions::new("local");
+    write_package(src.path(), "#let x = 1\n")?;
+    install_from(
+        packages.path(),
+        src.path(),
+        &options,
+        source(src.path()),
+        &DenyPrompter,
+    )?;
+
+    // Refusing to overwrite keeps the installed version
+    write_package(src.path(), "#let x = 2\n")?;
+    fs::remove_dir_all(src.path().join("template"))?;
+    fs::write(
+        src.path().join("typst.toml"),
+        MANIFEST.split("[template]").next().unwrap(),
+    )?;
+    let installed = packages.path().join("local/foo/0.1.0");
+    assert!(install_from(
+        packages.path(),
+        src.path(),
+        &options,
+        source(src.path()),
+        &DenyPrompter,
+    )
+    .is_err());
+    assert_eq!(
+        fs::read_to_string(installed.join("lib.typ"))?,
+        "#let x = 1\n"
+    );
+
+    let prompter =
+        ScriptedPrompter::new().answer("`@local/foo:0.1.0` already exists. Overwrite?", true);
+    install_from(
+        packages.path(),
+        src.path(),
+        &options,
+        source(src.path()),
+        &prompter,
+    )?;
+    assert_eq!(
+        fs::read_to_string(installed.join("lib.typ"))?,
+        "#let x = 2\n"
+    );
+    // Files of the old version are gone, not merged with the new ones
+    assert!(!installed.join("template").exists());
+    assert_eq!(entries(&packages.path().join("local/foo"))?, ["0.1.0"]);
+    Ok(())
+}
+
+#[test]
+fn test_install_failure_keeps_old_version() -> anyhow::Result<()> {
+    let src = tempfile::tempdir()?;
+    let packages = tempfile::tempdir()?;
+    write_package(src.path(), "#let x = 1\n")?;
+    let mut options = InstallOptions::new("local");
+    options.rename = Some("foo:0.2.0".parse::<InstallAs>()?);
+    install_from(
+        packages.path(),
+        src.path(),
+        &options,
+        source(src.path()),
+        &DenyPrompter,
+    )?;
+
+    // The template is not valid UTF-8, so rewriting its imports fails after
+    // the files have been staged
+    write_package(src.path(), "#let x = 2\n")?;
+    fs::write(src.path().join("template/main.typ"), b"\xff\xfe")?;
+    let prompter =
+        ScriptedPrompter::new().answer("`@local/foo:0.2.0` already exists. Overwrite?", true);
+    assert!(install_from(
+        packages.path(),
+        src.path(),
+        &options,
+        source(src.path()),
+        &prompter,
+    )
+    .is_err());
+
+    let installed = packages.path().join("local/foo/0.2.0");
+    assert_eq!(
+        fs::read_to_string(installed.join("lib.typ"))?,
+        "#let x = 1\n"
+    );
+    assert_eq!(
+        fs::read_to_string(installed.join("template/main.typ"))?,
+        "#import \"@local/foo:0.2.0\": *\n"
+    );
+    // No staging directory is left behind
+    assert_eq!(entries(&packages.path().join("local/foo"))?, ["0.2.0"]);
+    Ok(())
+}
+
+fn install_linked(
+    packages_dir: &Path,
+    src_dir: &Path,
+    link: LinkMode,
+) -> anyhow::Result<(PathBuf, InstallKind)> {
+    let mut options = InstallOptions::new("local");
+    options.link = link;
+    install_from(
+        packages_dir,
+        src_dir,
+        &options,
+        source(src_dir),
+        &DenyPrompter,
+    )?;
+    let receipt = read_receipt(packages_dir, &"@local/foo:0.1.0".parse().unwrap())?.unwrap();
+    Ok((packages_dir.join("local/foo/0.1.0"), receipt.kind))
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> anyhow::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let (a, b) = (fs::metadata(a)?, fs::metadata(b)?);
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+#[test]
+fn test_install_symlink() -> anyhow::Result<()> {
+    let src = tempfile::tempdir()?;
+    let packages = tempfile::tempdir()?;
+    write_package(src.path(), "#let x = 1\n")?;
+
+    let (installed, kind) = install_linked(packages.path(), src.path(), LinkMode::Symlink)?;
+    assert_eq!(kind, InstallKind::Symlink);
+    assert!(installed.is_symlink());
+    assert_eq!(fs::read_link(&installed)?, fs::canonicalize(src.path())?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_hardlink() -> anyhow::Result<()> {
+    let src = tempfile::tempdir()?;
+    let packages = tempfile::tempdir()?;
+    write_package(src.path(), "#let x = 1\n")?;
+
+    let (installed, kind) = install_linked(packages.path(), src.path(), LinkMode::Hardlink)?;
+    assert_eq!(kind, InstallKind::Hardlink);
+    assert!(!installed.is_symlink());
+    assert!(same_file(
+        &src.path().join("lib.typ"),
+        &installed.join("lib.typ")
+    )?);
+    assert!(same_file(
+        &src.path().join("template/main.typ"),
+        &installed.join("template/main.typ")
+    )?);
+    Ok(())
+}
+
+/// Hard links cannot cross file systems, so the install falls back to copies
+/// and the receipt says so.
+#[cfg(target_os = "linux")]
+#[test]
+fn test_install_hardlink_fallback() -> anyhow::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let packages = tempfile::tempdir()?;
+    let Ok(src) = tempfile::tempdir_in("/dev/shm") else {
+        return Ok(());
+    };
+    if fs::metadata(src.path())?.dev() == fs::metadata(packages.path())?.dev() {
+        return Ok(());
+    }
+    write_package(src.path(), "#let x = 1\n")?;
+
+    let (installed, kind) = install_linked(packages.path(), src.path(), LinkMode::Hardlink)?;
+    assert_eq!(kind, InstallKind::Copy);
+    assert_eq!(
+        fs::read_to_string(installed.join("lib.typ"))?,
+        "#let x = 1\n"
+    );
+    assert!(!same_file(
+        &src.path().join("lib.typ"),
+        &installed.join("lib.typ")
+    )?);
+    Ok(())
+}
+
+#[test]
+fn test_install_as_rewrites_self_imports() -> anyhow::Result<()> {
+    let src = tempfile::tempdir()?;
+    let packages = tempfile::tempdir()?;
+    write_package(src.path(), "#let x = 1\n")?;
+    let template = "#import \"@preview/foo:0.1.0\": *\n\
+        #import \"@local/foo:0.1.0\": x\n\
+        #import \"@preview/foo:0.0.9\": *\n\
+        #import \"@preview/foobar:0.1.0\": *\n\
+        #import \"@preview/bar:0.1.0\": *\n";
+    fs::write(src.path().join("template/main.typ"), template)?;
+
+    let mut options = InstallOptions::new("local");
+    options.link = LinkMode::Hardlink;
+    options.rename = Some("foo-rc:0.2.0".parse::<InstallAs>()?);
+    install_from(
+        packages.path(),
+        src.path(),
+        &options,
+        source(src.path()),
+        &DenyPrompter,
+    )?;
+
+    let installed = packages.path().join("local/foo-rc/0.2.0");
+    let manifest = read_manifest(&installed)?;
+    assert_eq!(manifest.package.name.as_str(), "foo-rc");
+    assert_eq!(manifest.package.version.to_string(), "0.2.0");
+    assert_eq!(
+        fs::read_to_string(installed.join("template/main.typ"))?,
+        "#import \"@local/foo-rc:0.2.0\": *\n\
+        #import \"@local/foo-rc:0.2.0\": x\n\
+        #import \"@preview/foo:0.0.9\": *\n\
+        #import \"@preview/foobar:0.1.0\": *\n\
+        #import \"@preview/bar:0.1.0\": *\n"
+    );
+
+    // The rewritten files replace the hard links instead of writing through
+    // them, so the source package is untouched
+    assert_eq!(fs::read_to_string(src.path().join("typst.toml"))?, MANIFEST);
+    assert_eq!(
+        fs::read_to_string(src.path().join("template/main.typ"))?,
+        template
+    );
+    let receipt = read_receipt(packages.path(), &"@local/foo-rc:0.2.0".parse().unwrap())?.unwrap();
+    assert_eq!(
+        receipt.files["template/main.typ"],
+        sha256_file(&installed.join("template/main.typ"))?
+    );
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::fs;
+
+use typship::commands::install::{install_from, InstallOptions};
+use typship::commands::list::{scan, EntryStatus};
+use typship::model::receipt::{InstallKind, Receipt, Source};
+use typship::prompt::DenyPrompter;
+use typship::utils::write_receipt;
+
+#[test]
+fn test_scan() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+
+    let installed = root.join("local/foo/0.1.0");
+    fs::create_dir_all(&installed)?;
+    fs::write(installed.join("typst.toml"), "12345")?;
+    let receipt = Receipt::new(
+        &"@local/foo:0.1.0".parse().unwrap(),
+        InstallKind::Copy,
+        Source::Path {
+            path: "/somewhere".into(),
+        },
+    );
+    write_receipt(root, &receipt)?;
+
+    fs::create_dir_all(root.join("team/bar/1.0.0"))?;
+    fs::create_dir_all(root.join("team/bar/.1.1.0-staging-abc"))?;
+    fs::create_dir_all(root.join("team/bar/not-a-version"))?;
+
+    let entries = scan(root, None)?;
+    let specs = entries
+        .iter()
+        .map(|e| (e.spec().to_string(), e.status, e.size))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        specs,
+        [
+            ("@local/foo:0.1.0".into(), EntryStatus::Installed, Some(5)),
+            ("@team/bar:1.0.0".into(), EntryStatus::Unmanaged, Some(0)),
+        ]
+    );
+
+    let entries = scan(root, Some("team"))?;
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].receipt.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_scan_modified() -> anyhow::Result<()> {
+    let src = tempfile::tempdir()?;
+    let root = tempfile::tempdir()?;
+    fs::write(
+        src.path().join("typst.toml"),
+        "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+    )?;
+    fs::write(src.path().join("lib.typ"), "#let x = 1\n")?;
+    install_from(
+        root.path(),
+        src.path(),
+        &InstallOptions::new("local"),
+        Source::Path {
+            path: src.path().to_path_buf(),
+        },
+        &DenyPrompter,
+    )?;
+    let status = || -> anyhow::Result<EntryStatus> { Ok(scan(root.path(), None)?[0].status) };
+    assert_eq!(status()?, EntryStatus::Installed);
+
+    let installed = root.path().join("local/foo/0.1.0");
+    fs::write(installed.join("lib.typ"), "#let x = 2\n")?;
+    assert_eq!(status()?, EntryStatus::Modified);
+    fs::write(installed.join("lib.typ"), "#let x = 1\n")?;
+    fs::write(installed.join("new.typ"), "")?;
+    assert_eq!(status()?, EntryStatus::Modified);
+    fs::remove_file(installed.join("new.typ"))?;
+    assert_eq!(status()?, EntryStatus::Installed);
+    Ok(())
+}
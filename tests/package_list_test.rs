@@ -0,0 +1,48 @@
+use std::fs;
+
+use typship::model::package_list::PackageList;
+
+fn parse(content: &str) -> anyhow::Result<PackageList> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("packages.toml");
+    fs::write(&path, content)?;
+    PackageList::from_file(&path)
+}
+
+#[test]
+fn test_package_list() {
+    let list = parse(
+        r#"
+[[package]]
+git = "https://example.com/team/packages"
+ref = "v0.3.0"
+path = "packages/foo"
+namespace = "team"
+
+[[package]]
+archive = "bar-0.1.0.tar.gz"
+
+[[package]]
+universe = "@preview/cetz:0.3.1"
+namespace = "vendor"
+"#,
+    )
+    .unwrap();
+    assert_eq!(list.package.len(), 3);
+    assert_eq!(list.package[0].checkout.as_deref(), Some("v0.3.0"));
+    assert_eq!(list.package[1].namespace, None);
+    assert_eq!(list.package[2].to_string(), "@preview/cetz:0.3.1");
+}
+
+#[test]
+fn test_invalid_package_list() {
+    // No source, or several
+    assert!(parse("[[package]]\nnamespace = \"team\"\n").is_err());
+    assert!(
+        parse("[[package]]\narchive = \"a.tar.gz\"\nuniverse = \"@preview/a:0.1.0\"\n").is_err()
+    );
+    // Git options without `git`
+    assert!(parse("[[package]]\narchive = \"a.tar.gz\"\npath = \"a\"\n").is_err());
+    // Unknown keys
+    assert!(parse("[[package]]\ngit = \"https://example.com/a\"\nbranch = \"main\"\n").is_err());
+}
@@ -0,0 +1,85 @@
+use typship::commands::init::{init, InitArgs};
+use typship::prompt::{DenyPrompter, Interaction, Prompter, ScriptedPrompter, TerminalPrompter};
+use typship::utils::read_manifest;
+
+#[test]
+fn test_scripted_init() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let prompter = ScriptedPrompter::new()
+        .answer("Enter the package author", "someone")
+        .answer("Enter the package version", "0.2.0")
+        .answer("Choose the package category", vec!["utility", "fun"])
+        .answer("Enter the package description", "A test package.")
+        .answer("Does the package have a template?", true);
+    init(
+        dir.path(),
+        &InitArgs {
+            name: Some("scripted".into()),
+        },
+        &prompter,
+    )?;
+
+    let manifest = read_manifest(dir.path())?;
+    assert_eq!(manifest.package.name, "scripted");
+    assert_eq!(manifest.package.version.to_string(), "0.2.0");
+    assert_eq!(manifest.package.authors, ["someone"]);
+    assert_eq!(manifest.package.categories, ["utility", "fun"]);
+    assert!(manifest.package.disciplines.is_empty());
+    assert_eq!(manifest.template.unwrap().path, "template");
+    assert!(dir.path().join("src/lib.typ").is_file());
+    Ok(())
+}
+
+#[test]
+fn test_scripted_invalid_answer() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let prompter = ScriptedPrompter::new().answer("Enter the package version", "latest");
+    let res = init(
+        dir.path(),
+        &InitArgs {
+            name: Some("scripted".into()),
+        },
+        &prompter,
+    );
+    assert!(res.is_err());
+    assert!(read_manifest(dir.path()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_deny_overwrite() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let args = InitArgs {
+        name: Some("denied".into()),
+    };
+    init(dir.path(), &args, &ScriptedPrompter::new())?;
+    assert!(init(dir.path(), &args, &DenyPrompter).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_assume_yes_on_terminal() -> anyhow::Result<()> {
+    // Nothing is asked, so no terminal is needed even if one is claimed
+    let prompter = TerminalPrompter::new(Interaction {
+        assume_yes: true,
+        interactive: true,
+    });
+    assert!(prompter.approve("Overwrite?")?);
+    assert!(prompter.confirm("Continue?", true)?);
+    assert!(!prompter.confirm("Also do the optional step?", false)?);
+    Ok(())
+}
+
+#[test]
+fn test_scripted_fallback() -> anyhow::Result<()> {
+    let prompter = ScriptedPrompter::new().answer("Overwrite?", false);
+    assert!(prompter.approve("Continue?").is_err());
+
+    let prompter = prompter.fallback(Interaction {
+        assume_yes: true,
+        interactive: false,
+    });
+    assert!(!prompter.approve("Overwrite?")?);
+    assert!(prompter.approve("Continue?")?);
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use typship::prompt::{DenyPrompter, Interaction, Prompter, ScriptedPrompter, TerminalPrompter};
+use typship::regs::universe::{
+    changelog_section, fork_setting, FileChanges, SubmissionMessage, DEFAULT_PR_TEMPLATE,
+};
+use typship::utils::git_blob_sha1;
+use typst_syntax::package::{PackageManifest, PackageVersion};
+
+const CHANGELOG: &str = "# Changelog
+
+## [Unreleased]
+
+- Work in progress
+
+## [v0.2.0] - 2024-05-01
+
+### Added
+
+- A `foo` function
+
+### Fixed
+
+- Spacing in `bar`
+
+## 0.1.0
+
+Initial release
+";
+
+fn v(s: &str) -> PackageVersion {
+    PackageVersion::from_str(s).unwrap()
+}
+
+#[test]
+fn test_changelog_section() {
+    assert_eq!(
+        changelog_section(CHANGELOG, &v("0.2.0")).unwrap(),
+        "### Added\n\n- A `foo` function\n\n### Fixed\n\n- Spacing in `bar`"
+    );
+    assert_eq!(
+        changelog_section(CHANGELOG, &v("0.1.0")).unwrap(),
+        "Initial release"
+    );
+    assert_eq!(changelog_section(CHANGELOG, &v("0.3.0")), None);
+    // Only whole versions count
+    assert_eq!(changelog_section("## 0.1.00\n\nx\n", &v("0.1.0")), None);
+}
+
+#[test]
+fn test_fill_pr_template() -> anyhow::Result<()> {
+    let template = "I am submitting\n\
+        - [ ] a new package\n\
+        - [ ] an update for a package\n\
+        \n\
+        Description: Explain what the package does.\n\
+        \n\
+        - [ ] tested my package locally\n\
+        - [ ] added a `README.md`\n\
+        \n\
+        - [ ] licensed the template directory freely\n";
+    let mut msg = SubmissionMessage {
+        is_new_package: false,
+        desc: "Fixed `bar`.".into(),
+        has_template: true,
+        has_manifest_keys: true,
+        has_readme: false,
+        has_license: true,
+    };
+    let prompter = ScriptedPrompter::new()
+        .answer("Checklist: tested my package locally", true)
+        .answer("Checklist: added a `README.md`", false);
+    assert_eq!(
+        msg.fill(template, &prompter)?,
+        "I am submitting\n\
+        - [ ] a new package\n\
+        - [x] an update for a package\n\
+        \n\
+        Description: Fixed `bar`.\n\
+        \n\
+        - [x] tested my package locally\n\
+        - [ ] added a `README.md`\n\
+        \n\
+        - [x] licensed the template directory freely\n"
+    );
+
+    // Only what typship verified is checked without asking, even with `--yes`
+    msg.has_readme = true;
+    let yes = TerminalPrompter::new(Interaction {
+        assume_yes: true,
+        interactive: false,
+    });
+    for prompter in [&DenyPrompter as &dyn Prompter, &yes] {
+        let body = msg.fill(DEFAULT_PR_TEMPLATE, prompter)?;
+        assert!(body.contains("Description: Fixed `bar`."));
+        assert!(body.contains("- [x] added a `typst.toml` file with all required keys"));
+        assert!(body.contains("- [x] added a `README.md` with documentation"));
+        assert!(body.contains("- [x] have chosen a license and added a `LICENSE` file"));
+        assert!(body.contains("- [ ] selected a name"));
+        assert!(body.contains("- [ ] tested my package locally on my system and it worked"));
+        assert!(body.contains("- [ ] `exclude`d PDFs"));
+        assert!(body.contains("- [x] ensured that my package is licensed"));
+    }
+
+    // A missing `LICENSE` file is asked about, as it may be linked instead
+    msg.has_license = false;
+    let prompter = ScriptedPrompter::new().answer(
+        "Checklist: have chosen a license and added a `LICENSE` file or linked one in my `README.md`",
+        true,
+    );
+    let body = msg.fill(DEFAULT_PR_TEMPLATE, &prompter)?;
+    assert!(body.contains("- [x] have chosen a license"));
+    assert!(body.contains("- [x] ensured that my package is licensed"));
+    Ok(())
+}
+
+#[test]
+fn test_file_changes() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("typst.toml"), "hello\n")?;
+    std::fs::write(dir.path().join("lib.typ"), "#let x = 2\n")?;
+    std::fs::write(dir.path().join("new.typ"), "")?;
+    // Same as `git hash-object`
+    assert_eq!(
+        git_blob_sha1(b"hello\n"),
+        "ce013625030ba8dba906f756967f9e9ca394464a"
+    );
+
+    let remote = BTreeMap::from([
+        (PathBuf::from("typst.toml"), git_blob_sha1(b"hello\n")),
+        (PathBuf::from("lib.typ"), git_blob_sha1(b"#let x = 1\n")),
+        (PathBuf::from("old.typ"), git_blob_sha1(b"")),
+    ]);
+    let files = ["typst.toml", "lib.typ", "new.typ"].map(PathBuf::from);
+    let changes = FileChanges::new(&remote, dir.path(), &files)?;
+    assert_eq!(changes.added, [PathBuf::from("new.typ")]);
+    assert_eq!(changes.modified, [PathBuf::from("lib.typ")]);
+    assert_eq!(changes.removed, [PathBuf::from("old.typ")]);
+    assert_eq!(
+        changes.to_string(),
+        "- Added `new.typ`\n- Modified `lib.typ`\n- Removed `old.typ`"
+    );
+
+    assert!(FileChanges::new(&remote, dir.path(), &files[..1])?
+        .added
+        .is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_fork_setting() -> anyhow::Result<()> {
+    let manifest = |tool: &str| -> anyhow::Result<PackageManifest> {
+        Ok(toml::from_str(&format!(
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n{}",
+            tool
+        ))?)
+    };
+    let plain = manifest("")?;
+    let with_fork = manifest("[tool.typship]\nfork = \"me/manifest\"\n")?;
+
+    assert_eq!(fork_setting(None, &plain, None), None);
+    assert_eq!(
+        fork_setting(None, &plain, Some("config")).as_deref(),
+        Some("config")
+    );
+    // When the sources disagree, `--fork` wins over the manifest, and the
+    // manifest over the config
+    assert_eq!(
+        fork_setting(None, &with_fork, Some("config")).as_deref(),
+        Some("me/manifest")
+    );
+    assert_eq!(
+        fork_setting(Some("me/cli"), &with_fork, Some("config")).as_deref(),
+        Some("me/cli")
+    );
+    assert_eq!(
+        fork_setting(Some("me/cli"), &plain, None).as_deref(),
+        Some("me/cli")
+    );
+    // A `[tool.typship]` without a fork falls through to the config
+    let other_tool = manifest("[tool.typship]\n[tool.other]\nfork = \"x\"\n")?;
+    assert_eq!(
+        fork_setting(None, &other_tool, Some("config")).as_deref(),
+        Some("config")
+    );
+    Ok(())
+}
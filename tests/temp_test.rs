@@ -0,0 +1,29 @@
+use std::fs;
+
+use typship::utils::temp::WorkDir;
+
+#[test]
+fn test_work_dir_cleanup() {
+    let a = WorkDir::new("test", false).unwrap();
+    let b = WorkDir::new("test", false).unwrap();
+    assert_ne!(a.path(), b.path());
+
+    let path = a.path().to_path_buf();
+    fs::write(path.join("file"), "content").unwrap();
+    assert!(path.with_extension("lock").is_file());
+    drop(a);
+    assert!(!path.exists());
+    assert!(!path.with_extension("lock").exists());
+    assert!(b.path().is_dir());
+}
+
+#[test]
+fn test_work_dir_keep() {
+    let dir = WorkDir::new("test", true).unwrap();
+    let path = dir.path().to_path_buf();
+    drop(dir);
+    assert!(path.is_dir());
+    // A kept directory is no longer locked, and so never swept
+    assert!(!path.with_extension("lock").exists());
+    fs::remove_dir_all(path).unwrap();
+}
@@ -0,0 +1,90 @@
+use std::fs;
+
+use typship::commands::uninstall::{uninstall_from, PackageMatcher};
+use typship::model::receipt::{InstallKind, Receipt, Source};
+use typship::prompt::{DenyPrompter, Interaction, TerminalPrompter};
+use typship::utils::{receipt_file, write_receipt};
+
+const YES: TerminalPrompter = TerminalPrompter::new(Interaction {
+    assume_yes: true,
+    interactive: false,
+});
+
+fn install_fake(root: &std::path::Path, spec: &str) -> anyhow::Result<()> {
+    let spec = spec.parse().unwrap();
+    let receipt = Receipt::new(
+        &spec,
+        InstallKind::Copy,
+        Source::Path {
+            path: "/somewhere".into(),
+        },
+    );
+    let dir = root
+        .join(spec.namespace.as_str())
+        .join(spec.name.as_str())
+        .join(spec.version.to_string());
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("typst.toml"), "")?;
+    write_receipt(root, &receipt)
+}
+
+#[test]
+fn test_matcher() {
+    let m: PackageMatcher = "@local/foo:0.2.0".parse().unwrap();
+    assert_eq!(m.version.unwrap().to_string(), "0.2.0");
+    assert!("@local/foo"
+        .parse::<PackageMatcher>()
+        .unwrap()
+        .version
+        .is_none());
+    assert!("@team/foo:*"
+        .parse::<PackageMatcher>()
+        .unwrap()
+        .version
+        .is_none());
+    assert!("local/foo".parse::<PackageMatcher>().is_err());
+    assert!("@local/foo:latest".parse::<PackageMatcher>().is_err());
+}
+
+#[test]
+fn test_uninstall() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+    install_fake(root, "@local/foo:0.1.0")?;
+    install_fake(root, "@local/foo:0.2.0")?;
+    install_fake(root, "@local/bar:1.0.0")?;
+
+    let one = "@local/foo:0.2.0".parse()?;
+    assert!(uninstall_from(root, &one, false, &DenyPrompter).is_err());
+    uninstall_from(root, &one, false, &YES)?;
+    assert!(!root.join("local/foo/0.2.0").exists());
+    assert!(root.join("local/foo/0.1.0").exists());
+    assert!(!receipt_file(root, &"@local/foo:0.2.0".parse().unwrap()).exists());
+    assert!(uninstall_from(root, &one, false, &YES).is_err());
+
+    uninstall_from(root, &"@local/foo:*".parse()?, false, &YES)?;
+    assert!(!root.join("local/foo").exists());
+    assert!(root.join("local/bar/1.0.0").exists());
+
+    uninstall_from(root, &"@local/bar".parse()?, false, &YES)?;
+    assert!(!root.join("local").exists());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_uninstall_keeps_links() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root = dir.path();
+    let target = tempfile::tempdir()?;
+    fs::create_dir_all(root.join("preview/foo"))?;
+    std::os::unix::fs::symlink(target.path(), root.join("preview/foo/0.1.0"))?;
+
+    let matcher = "@preview/foo".parse()?;
+    assert!(uninstall_from(root, &matcher, false, &YES).is_err());
+    assert!(root.join("preview/foo/0.1.0").is_symlink());
+    uninstall_from(root, &matcher, true, &YES)?;
+    assert!(!root.join("preview").exists());
+    assert!(target.path().is_dir());
+    Ok(())
+}
@@ -0,0 +1,397 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use common::{MockServer, Response};
+use secrecy::SecretString;
+use typship::config::UniverseConfig;
+use typship::prompt::{DenyPrompter, Prompter, ScriptedPrompter};
+use typship::regs::universe::{
+    create_fork, package_versions, packages, pending_list, sync_fork, upload_files_git_data,
+    PackageSubmission, Upstream,
+};
+use typship::utils::git_blob_sha1;
+
+#[test]
+fn test_default_upstream() {
+    let upstream = Upstream::default();
+    assert_eq!(
+        (upstream.owner.as_str(), upstream.repo.as_str()),
+        ("typst", "packages")
+    );
+    assert_eq!(upstream.branch, "main");
+    assert_eq!(upstream.api_url, None);
+    assert_eq!(upstream.host(), "github.com");
+    assert_eq!(
+        upstream.git_url("me", "packages"),
+        "https://github.com/me/packages.git"
+    );
+}
+
+#[test]
+fn test_enterprise_upstream() {
+    let upstream = Upstream::from_config(&UniverseConfig {
+        owner: Some("mirrors".into()),
+        repo: Some("typst-packages".into()),
+        branch: Some("staging".into()),
+        api_url: Some("https://github.example.com/api/v3/".into()),
+        ..Default::default()
+    });
+    assert_eq!(upstream.branch, "staging");
+    assert_eq!(
+        upstream.api_url.as_deref(),
+        Some("https://github.example.com/api/v3")
+    );
+    assert_eq!(upstream.web_url, "https://github.example.com");
+    assert_eq!(upstream.host(), "github.example.com");
+    assert_eq!(
+        upstream.git_url("me", "typst-packages"),
+        "https://github.example.com/me/typst-packages.git"
+    );
+
+    // A mock API server on its own
+    let upstream = Upstream::from_config(&UniverseConfig {
+        api_url: Some("http://127.0.0.1:8080".into()),
+        web_url: Some("http://127.0.0.1:8081/".into()),
+        ..Default::default()
+    });
+    assert_eq!(upstream.web_url, "http://127.0.0.1:8081");
+    assert_eq!(upstream.host(), "127.0.0.1");
+}
+
+/// Serve the GitHub API routes used by the lookups, with more packages than
+/// the contents API lists and more open PRs than fit on a page.
+fn mock_api() -> MockServer {
+    let tree = |entries: Vec<(String, &str)>| {
+        let entries = entries
+            .into_iter()
+            .map(|(name, sha)| serde_json::json!({"path": name, "type": "tree", "sha": sha}))
+            .collect::<Vec<_>>();
+        serde_json::json!({"tree": entries, "truncated": false})
+    };
+    MockServer::start(move |request| {
+        let base = &request.server_url;
+        let pulls = |numbers: std::ops::Range<u64>| {
+            let pulls = numbers
+                .map(|n| {
+                    serde_json::json!({
+                        "url": format!("{}/pulls/{}", base, n), "id": n, "number": n,
+                        "title": format!("pkg-{}:0.1.0", n), "locked": false,
+                        "maintainer_can_modify": false,
+                        "head": {"ref": "x", "sha": "x"}, "base": {"ref": "main", "sha": "x"},
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::json!(pulls)
+        };
+        match request.path.as_str() {
+            "/repos/o/r/git/trees/main" => Response::json(tree(vec![("packages".into(), "t1")])),
+            "/repos/o/r/git/trees/t1" => Response::json(tree(vec![("preview".into(), "t2")])),
+            "/repos/o/r/git/trees/t2" => Response::json(tree(
+                (0..1500).map(|i| (format!("pkg-{}", i), "t3")).collect(),
+            )),
+            "/repos/o/r/git/trees/t3" => Response::json(tree(vec![("0.1.0".into(), "t4")])),
+            p if p.starts_with("/repos/o/r/pulls") && p.contains("page=2") => {
+                Response::json(pulls(100..130))
+            }
+            p if p.starts_with("/repos/o/r/pulls") => Response::json(pulls(0..100)).header(
+                "Link",
+                &format!(
+                    "<{}/repos/o/r/pulls?state=open&per_page=100&page=2>; rel=\"next\"",
+                    base
+                ),
+            ),
+            _ => Response::json(serde_json::json!({})),
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_paginated_lookups() -> anyhow::Result<()> {
+    let server = mock_api();
+    let upstream = Upstream::from_config(&UniverseConfig {
+        owner: Some("o".into()),
+        repo: Some("r".into()),
+        api_url: Some(server.url.clone()),
+        ..Default::default()
+    });
+
+    let token = SecretString::from("secret");
+    let packages = packages(&upstream, Some(&token)).await?;
+    assert_eq!(packages.len(), 1500);
+    assert!(packages.contains(&"pkg-1499".to_string()));
+    assert_eq!(
+        package_versions(&upstream, None, "pkg-1499").await?,
+        ["0.1.0"]
+    );
+    assert!(package_versions(&upstream, None, "missing").await.is_err());
+
+    let prs = pending_list(&upstream, Some(&token)).await?;
+    assert_eq!(prs.len(), 130);
+    assert_eq!(prs.last().unwrap().title.as_deref(), Some("pkg-129:0.1.0"));
+
+    // Only the lookups given the token send it
+    let authorized = server
+        .requests()
+        .iter()
+        .map(|r| r.header("Authorization").is_some())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        authorized,
+        [[true; 3].as_slice(), &[false; 7], &[true; 2]].concat()
+    );
+    Ok(())
+}
+
+/// Serve the Git Data API routes used by the `git-data` upload method for the
+/// branch `foo-0.1.0` of `me/packages`.
+fn mock_git_data_api() -> MockServer {
+    let mut blobs = 0;
+    MockServer::start(move |request| {
+        let response = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/repos/me/packages/git/ref/heads/foo-0.1.0") => serde_json::json!({
+                "ref": "refs/heads/foo-0.1.0", "node_id": "x", "url": "http://x",
+                "object": {"type": "commit", "sha": "parent", "url": "http://x"},
+            }),
+            ("GET", "/repos/me/packages/git/commits/parent") => {
+                serde_json::json!({"sha": "parent", "tree": {"sha": "base-tree"}})
+            }
+            ("POST", "/repos/me/packages/git/blobs") => {
+                blobs += 1;
+                serde_json::json!({ "sha": format!("blob-{}", blobs) })
+            }
+            ("POST", "/repos/me/packages/git/trees") => serde_json::json!({"sha": "tree"}),
+            ("POST", "/repos/me/packages/git/commits") => serde_json::json!({"sha": "commit"}),
+            _ => serde_json::json!({}),
+        };
+        Response::json(response)
+    })
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_upload_git_data() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("typst.toml"), "unchanged\n")?;
+    std::fs::write(dir.path().join("lib.typ"), "#let x = 1\n")?;
+    std::fs::write(dir.path().join("build.sh"), "#!/bin/sh\n")?;
+    std::fs::set_permissions(
+        dir.path().join("build.sh"),
+        std::fs::Permissions::from_mode(0o755),
+    )?;
+    let files = ["typst.toml", "lib.typ", "build.sh"].map(PathBuf::from);
+    let remote = BTreeMap::from([
+        (PathBuf::from("typst.toml"), git_blob_sha1(b"unchanged\n")),
+        (PathBuf::from("old.typ"), git_blob_sha1(b"")),
+    ]);
+
+    let server = mock_git_data_api();
+    let upstream = Upstream::from_config(&UniverseConfig {
+        api_url: Some(server.url.clone()),
+        ..Default::default()
+    });
+    let submission = PackageSubmission {
+        name: "foo".into(),
+        version: "0.1.0".parse().unwrap(),
+    };
+    upload_files_git_data(
+        &upstream.client(None)?,
+        "me",
+        "packages",
+        &submission,
+        dir.path(),
+        &files,
+        &remote,
+        "[Typship] Update package foo:0.1.0",
+    )
+    .await?;
+
+    let requests = server.requests();
+    let route = |method: &str, path: &str| {
+        (
+            method.to_string(),
+            format!("/repos/me/packages/git/{}", path),
+        )
+    };
+    assert_eq!(
+        requests
+            .iter()
+            .map(|r| (r.method.clone(), r.path.clone()))
+            .collect::<Vec<_>>(),
+        [
+            route("GET", "ref/heads/foo-0.1.0"),
+            route("GET", "commits/parent"),
+            route("POST", "blobs"),
+            route("POST", "blobs"),
+            route("POST", "trees"),
+            route("POST", "commits"),
+            route("PATCH", "refs/heads/foo-0.1.0"),
+        ]
+    );
+    let path = "packages/preview/foo/0.1.0";
+    assert_eq!(
+        requests[4].json(),
+        serde_json::json!({
+            "base_tree": "base-tree",
+            "tree": [
+                {"path": format!("{}/lib.typ", path), "mode": "100644", "type": "blob", "sha": "blob-1"},
+                {"path": format!("{}/build.sh", path), "mode": "100755", "type": "blob", "sha": "blob-2"},
+                {"path": format!("{}/old.typ", path), "mode": "100644", "type": "blob", "sha": null},
+            ],
+        })
+    );
+    assert_eq!(
+        requests[5].json(),
+        serde_json::json!({
+            "message": "[Typship] Update package foo:0.1.0",
+            "tree": "tree",
+            "parents": ["parent"],
+        })
+    );
+    assert_eq!(
+        requests[6].json(),
+        serde_json::json!({"sha": "commit", "force": false})
+    );
+    Ok(())
+}
+
+/// A mock API where `main` of the upstream `typst/packages` and of
+/// `org/packages` is at `upstream`, and of the fork `me/packages` at
+/// `fork_sha`, if it exists. Forks are
+/// created as `forked_name`.
+fn mock_fork_api(fork_sha: Option<&'static str>, forked_name: &'static str) -> MockServer {
+    let branch = |sha: &str| {
+        Response::json(serde_json::json!({
+            "ref": "refs/heads/main", "node_id": "x", "url": "http://x",
+            "object": {"type": "commit", "sha": sha, "url": "http://x"},
+        }))
+    };
+    MockServer::start(
+        move |request| match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/repos/typst/packages/git/ref/heads/main")
+            | ("GET", "/repos/org/packages/git/ref/heads/main") => branch("upstream"),
+            ("GET", "/repos/me/packages/git/ref/heads/main") => match fork_sha {
+                Some(sha) => branch(sha),
+                None => Response::new("404 Not Found"),
+            },
+            ("POST", "/repos/typst/packages/forks") => Response::json(serde_json::json!({
+                "id": 1, "name": forked_name, "full_name": format!("me/{}", forked_name),
+                "url": "http://x",
+            })),
+            _ => Response::json(serde_json::json!({})),
+        },
+    )
+}
+
+fn mock_upstream(server: &MockServer) -> Upstream {
+    Upstream::from_config(&UniverseConfig {
+        api_url: Some(server.url.clone()),
+        ..Default::default()
+    })
+}
+
+/// The method and path of the requests received by `server`.
+fn routes(server: &MockServer) -> Vec<(String, String)> {
+    server
+        .requests()
+        .iter()
+        .map(|r| (r.method.clone(), r.path.clone()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_create_fork() -> anyhow::Result<()> {
+    let server = mock_fork_api(Some("upstream"), "packages");
+    let upstream = mock_upstream(&server);
+    create_fork(&upstream.client(None)?, &upstream, "me", ("me", "packages")).await?;
+    let requests = server.requests();
+    assert_eq!(
+        routes(&server),
+        [
+            ("POST".into(), "/repos/typst/packages/forks".into()),
+            ("GET".into(), "/repos/me/packages/git/ref/heads/main".into()),
+        ]
+    );
+    assert_eq!(
+        requests[0].json(),
+        serde_json::json!({"name": "packages", "default_branch_only": true})
+    );
+
+    // Forks to an organization name it
+    let server = mock_fork_api(Some("upstream"), "packages");
+    let upstream = mock_upstream(&server);
+    create_fork(
+        &upstream.client(None)?,
+        &upstream,
+        "Me",
+        ("org", "packages"),
+    )
+    .await?;
+    assert_eq!(
+        server.requests()[0].json(),
+        serde_json::json!({"organization": "org", "name": "packages", "default_branch_only": true})
+    );
+
+    // GitHub returns the existing fork under another name
+    let server = mock_fork_api(Some("upstream"), "typst-packages");
+    let upstream = mock_upstream(&server);
+    let error = create_fork(&upstream.client(None)?, &upstream, "me", ("me", "packages"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "typst/packages is already forked to `me/typst-packages`. Pass it with `--fork`."
+    );
+    Ok(())
+}
+
+/// Sync `me/packages` with the upstream at `upstream`.
+async fn sync(server: &MockServer, prompter: &dyn Prompter) -> anyhow::Result<()> {
+    let upstream = mock_upstream(server);
+    let client = upstream.client(None)?;
+    sync_fork(&client, ("me", "packages"), &upstream, "upstream", prompter).await
+}
+
+#[tokio::test]
+async fn test_sync_fork() -> anyhow::Result<()> {
+    let question =
+        "`main` of your fork is not up to date with typst/packages. Do you want to fast-forward it?";
+    let get_fork = (
+        "GET".to_string(),
+        "/repos/me/packages/git/ref/heads/main".to_string(),
+    );
+
+    // Up to date, or without the branch, nothing is asked or changed
+    for fork_sha in [Some("upstream"), None] {
+        let server = mock_fork_api(fork_sha, "packages");
+        sync(&server, &ScriptedPrompter::new()).await?;
+        assert_eq!(routes(&server), std::slice::from_ref(&get_fork));
+    }
+
+    // Behind, the branch is fast-forwarded only when confirmed
+    let server = mock_fork_api(Some("old"), "packages");
+    sync(&server, &DenyPrompter).await?;
+    assert_eq!(routes(&server), std::slice::from_ref(&get_fork));
+
+    let server = mock_fork_api(Some("old"), "packages");
+    sync(&server, &ScriptedPrompter::new().answer(question, true)).await?;
+    let requests = server.requests();
+    assert_eq!(
+        routes(&server),
+        [
+            get_fork,
+            (
+                "PATCH".into(),
+                "/repos/me/packages/git/refs/heads/main".into()
+            ),
+        ]
+    );
+    assert_eq!(
+        requests[1].json(),
+        serde_json::json!({"sha": "upstream", "force": false})
+    );
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::str::FromStr;
+
+use typship::model::version::{version_from_tag, VersionReq};
+use typst_syntax::package::PackageVersion;
+
+fn v(s: &str) -> PackageVersion {
+    PackageVersion::from_str(s).unwrap()
+}
+
+fn req(s: &str) -> VersionReq {
+    VersionReq::from_str(s).unwrap()
+}
+
+#[test]
+fn test_caret() {
+    let r = req("^0.3");
+    assert!(r.matches(&v("0.3.0")));
+    assert!(r.matches(&v("0.3.9")));
+    assert!(!r.matches(&v("0.4.0")));
+    assert!(!r.matches(&v("0.2.9")));
+
+    // A bare version means `^`
+    let r = req("1.2");
+    assert!(r.matches(&v("1.9.0")));
+    assert!(!r.matches(&v("1.1.0")));
+    assert!(!r.matches(&v("2.0.0")));
+
+    let r = req("^0.0.3");
+    assert!(r.matches(&v("0.0.3")));
+    assert!(!r.matches(&v("0.0.4")));
+}
+
+#[test]
+fn test_tilde_and_ranges() {
+    let r = req("~1.2.0");
+    assert!(r.matches(&v("1.2.5")));
+    assert!(!r.matches(&v("1.3.0")));
+
+    let r = req(">=0.2, <0.4");
+    assert!(r.matches(&v("0.2.0")));
+    assert!(r.matches(&v("0.3.7")));
+    assert!(!r.matches(&v("0.4.0")));
+    assert_eq!(r.to_string(), ">=0.2, <0.4");
+
+    assert!(req("*").matches(&v("9.9.9")));
+    assert!(VersionReq::from_str("^").is_err());
+    assert!(VersionReq::from_str("^x.y").is_err());
+}
+
+#[test]
+fn test_best_match() {
+    let versions = ["0.2.0", "0.3.0", "0.3.2", "0.4.0"].map(v);
+    assert_eq!(req("^0.3").best_match(&versions), Some(&v("0.3.2")));
+    assert_eq!(req("*").best_match(&versions), Some(&v("0.4.0")));
+    assert_eq!(req("^1").best_match(&versions), None);
+}
+
+#[test]
+fn test_version_from_tag() {
+    assert_eq!(version_from_tag("v0.3.1"), Some(v("0.3.1")));
+    assert_eq!(version_from_tag("0.3.1"), Some(v("0.3.1")));
+    assert_eq!(version_from_tag("release"), None);
+}
@@ -1,15 +1,11 @@
+mod common;
+
 use std::collections::HashSet;
+use std::fs;
 use std::io;
-use std::path::PathBuf;
-
-use typship::utils::walkers::{walker_install, walker_publish};
 
-fn walker_test_path() -> PathBuf {
-    <&str as Into<PathBuf>>::into(file!())
-        .parent()
-        .unwrap()
-        .join("walker_test")
-}
+use common::walker_test_path;
+use typship::utils::walkers::{find_packages, walker_install, walker_publish};
 
 fn test_files(
     files: Vec<&str>,
@@ -59,3 +55,20 @@ fn test_exclude() -> io::Result<()> {
         walker.into_iter().collect(),
     )
 }
+
+#[test]
+fn test_find_packages() -> io::Result<()> {
+    let root = tempfile::tempdir()?;
+    let root = root.path();
+    for dir in ["a", "nested/b", ".hidden/c"] {
+        fs::create_dir_all(root.join(dir))?;
+        fs::write(root.join(dir).join("typst.toml"), "")?;
+    }
+    fs::create_dir_all(root.join("docs"))?;
+    fs::write(root.join("docs/README.md"), "")?;
+
+    assert_eq!(find_packages(root), [root.join("a"), root.join("nested/b")]);
+    assert_eq!(find_packages(&root.join("a")), [root.join("a")]);
+    assert!(find_packages(&root.join("docs")).is_empty());
+    Ok(())
+}